@@ -0,0 +1,151 @@
+use crate::config;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use ropey::Rope;
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+//改行コード。読み込んだときの形式で保存する
+//Mixedは\r\nと\nが混在するファイルで、\rを行の内容として持ったままバイト列どおりに保存する
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Mixed,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Self::Lf | Self::Mixed => "\n",
+            Self::Crlf => "\r\n",
+        };
+    }
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+            Self::Mixed => "LF/CRLF混在",
+        };
+    }
+}
+
+//ファイルの内容をropeで持つ。行は改行(\n)で区切り、最後の行の後ろには改行を持たない
+//位置は(行, 行内のバイト位置)で表す
+//文字コード、改行コード、BOM、末尾の改行の有無は保存するときに元に戻す
+#[derive(Clone)]
+pub struct Buffer {
+    rope: Rope,
+    pub line_ending: LineEnding,
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+    pub final_newline: bool,
+}
+
+impl Buffer {
+    //新しいファイルは設定の文字コードと改行コードで保存する。UTF-16はBOMを付ける
+    pub fn new() -> Self {
+        let mut buffer = Self::from_str("");
+        let config = config::current();
+        buffer.encoding = config.encoding;
+        buffer.line_ending = config.line_ending;
+        buffer.bom = buffer.encoding == UTF_16LE || buffer.encoding == UTF_16BE;
+        return buffer;
+    }
+    pub fn from_str(text: &str) -> Self {
+        let rope = Rope::from_str(text);
+        let line_ending = LineEnding::Lf;
+        let encoding = UTF_8;
+        let bom = false;
+        let final_newline = true;
+        return Self {
+            rope,
+            line_ending,
+            encoding,
+            bom,
+            final_newline,
+        };
+    }
+    pub fn len_lines(&self) -> usize {
+        return self.rope.len_lines();
+    }
+    //改行を含まない行iの文字列
+    pub fn line(&self, i: usize) -> Cow<'_, str> {
+        let mut slice = self.rope.line(i);
+        let l = slice.len_chars();
+        if 0 < l && slice.char(l - 1) == '\n' {
+            slice = slice.slice(..l - 1);
+        }
+        return match slice.as_str() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(slice.to_string()),
+        };
+    }
+    pub fn lines(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        return (0..self.len_lines()).map(|i| self.line(i));
+    }
+    //(y, x)のバッファ先頭からのバイト位置
+    #[cfg(feature = "tree-sitter")]
+    pub fn byte(&self, y: usize, x: usize) -> usize {
+        return self.rope.line_to_byte(y) + x;
+    }
+    fn char_index(&self, y: usize, x: usize) -> usize {
+        return self.rope.byte_to_char(self.rope.line_to_byte(y) + x);
+    }
+    //(y, x)からremovedと同じ長さを取り除いてinsertedを挿入する
+    pub fn replace(&mut self, y: usize, x: usize, removed: &str, inserted: &str) {
+        let start = self.char_index(y, x);
+        let end = start + removed.chars().count();
+        self.rope.remove(start..end);
+        self.rope.insert(start, inserted);
+    }
+    //startからendまでの文字列
+    pub fn text(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let start = self.char_index(start.0, start.1);
+        let end = self.char_index(end.0, end.1);
+        return self.rope.slice(start..end).to_string();
+    }
+    //UTF-8はそのまま書き出し、それ以外は全体を変換してから書き出す
+    pub fn write_to<T: Write>(&self, mut writer: T) -> io::Result<()> {
+        if self.encoding != UTF_8 {
+            let text = self.chunks().collect::<String>();
+            return writer.write_all(&encode(&text, self.encoding)?);
+        }
+        for chunk in self.chunks() {
+            writer.write_all(chunk.as_bytes())?;
+        }
+        return Ok(());
+    }
+    //BOMと改行コードを付けた、保存する内容
+    fn chunks(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        let bom = self.bom.then_some(Cow::Borrowed("\u{feff}"));
+        let final_newline = self
+            .final_newline
+            .then_some(Cow::Borrowed(self.line_ending.as_str()));
+        let chunks = self.rope.chunks().map(|chunk| match self.line_ending {
+            LineEnding::Lf | LineEnding::Mixed => Cow::Borrowed(chunk),
+            LineEnding::Crlf => Cow::Owned(chunk.replace('\n', "\r\n")),
+        });
+        return bom.into_iter().chain(chunks).chain(final_newline);
+    }
+}
+
+//encoding_rsはUTF-16に書き出せないので自分で変換する
+//表せない文字があるときは保存しない
+fn encode(text: &str, encoding: &'static Encoding) -> io::Result<Vec<u8>> {
+    if encoding == UTF_16LE {
+        return Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect());
+    }
+    if encoding == UTF_16BE {
+        return Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect());
+    }
+    let (b, _, errors) = encoding.encode(text);
+    if errors {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}で表せない文字があります", encoding.name()),
+        ));
+    }
+    return Ok(b.into_owned());
+}
@@ -0,0 +1,203 @@
+use crate::{file::Content, hex::Hex, normal::Normal, screen::Screen, Buffer, Cursor, Dir, Mode};
+use std::fs::canonicalize;
+
+//開いているファイルの表示。テキストはNormal、バイナリはHexで扱う
+pub enum View {
+    Text(Box<Normal>),
+    Binary(Hex),
+}
+
+impl View {
+    pub fn new(content: Content) -> Self {
+        return match content {
+            Content::Text(buffer) => Self::Text(Box::new(Normal::new(buffer))),
+            Content::Binary(bytes) => {
+                let mut hex = Hex::new();
+                hex.set_bytes(bytes);
+                Self::Binary(hex)
+            }
+        };
+    }
+    pub fn output(&mut self, screen: &mut Screen, path: &str) {
+        match self {
+            Self::Text(normal) => normal.output(screen, path),
+            Self::Binary(hex) => hex.output(screen, path),
+        }
+    }
+    pub fn resize(&mut self, width: u16, height: u16) {
+        match self {
+            Self::Text(normal) => normal.resize(width, height),
+            Self::Binary(hex) => hex.resize(width, height),
+        }
+    }
+    pub fn redraw(&mut self) {
+        if let Self::Text(normal) = self {
+            normal.redraw();
+        }
+    }
+    pub fn view(&self) -> (Cursor, usize) {
+        return match self {
+            Self::Text(normal) => normal.view(),
+            Self::Binary(hex) => hex.view(),
+        };
+    }
+    pub fn set_view(&mut self, view: (Cursor, usize)) {
+        match self {
+            Self::Text(normal) => normal.set_view(view),
+            Self::Binary(hex) => hex.set_view(view),
+        }
+    }
+    pub fn set_msg(&mut self, msg: String) {
+        match self {
+            Self::Text(normal) => normal.msg = msg,
+            Self::Binary(hex) => hex.msg = msg,
+        }
+    }
+    pub fn modified(&self) -> bool {
+        return match self {
+            Self::Text(normal) => normal.modified(),
+            Self::Binary(hex) => hex.modified(),
+        };
+    }
+    fn set_index(&mut self, index: (usize, usize)) {
+        match self {
+            Self::Text(normal) => normal.index = index,
+            Self::Binary(hex) => hex.index = index,
+        }
+    }
+}
+
+//開いているファイルの一覧。今のファイルのパスはdir.pathに置き、切り替えるときに入れ替える
+pub struct Buffers {
+    views: Vec<View>,
+    paths: Vec<String>,
+    pub current: usize,
+}
+
+impl Buffers {
+    pub fn new(view: View) -> Self {
+        let views = vec![view];
+        let paths = vec![String::new()];
+        let current = 0;
+        let mut buffers = Self {
+            views,
+            paths,
+            current,
+        };
+        buffers.set_index();
+        return buffers;
+    }
+    pub fn len(&self) -> usize {
+        return self.views.len();
+    }
+    pub fn view(&mut self) -> &mut View {
+        return &mut self.views[self.current];
+    }
+    pub fn normal(&mut self) -> Option<&mut Normal> {
+        return match self.view() {
+            View::Text(normal) => Some(normal.as_mut()),
+            View::Binary(_) => None,
+        };
+    }
+    pub fn hex(&mut self) -> Option<&mut Hex> {
+        return match self.view() {
+            View::Text(_) => None,
+            View::Binary(hex) => Some(hex),
+        };
+    }
+    //今のファイルを表示するモード
+    pub fn mode(&self) -> Mode {
+        return match self.views[self.current] {
+            View::Text(_) => Mode::Normal,
+            View::Binary(_) => Mode::Hex,
+        };
+    }
+    //一覧に表示する(パス, 変更済みか)
+    pub fn entries(&self, dir: &Dir) -> Vec<(String, bool)> {
+        return (0..self.len())
+            .map(|i| {
+                let path = if i == self.current {
+                    &dir.path
+                } else {
+                    &self.paths[i]
+                };
+                (path.clone(), self.views[i].modified())
+            })
+            .collect();
+    }
+    //pathを開いていればその番号。相対パスと絶対パスのように書き方が違っても同じファイルとみなす
+    pub fn find(&self, path: &str, dir: &Dir) -> Option<usize> {
+        let path = canonicalize(path).ok()?;
+        return self
+            .entries(dir)
+            .iter()
+            .position(|(p, _)| !p.is_empty() && canonicalize(p).is_ok_and(|p| p == path));
+    }
+    //新しいファイルを一覧の最後に加えて切り替える
+    pub fn add(&mut self, view: View, path: String, dir: &mut Dir) {
+        self.views.push(view);
+        self.paths.push(path);
+        self.switch(self.len() - 1, dir);
+    }
+    //バッファiを、別の画面でのカーソルと表示位置viewで描く。描いた後は元の状態に戻す
+    pub fn draw(&mut self, i: usize, view: (Cursor, usize), screen: &mut Screen, dir: &Dir) {
+        let path = if i == self.current {
+            dir.path.clone()
+        } else {
+            self.paths[i].clone()
+        };
+        let v = &mut self.views[i];
+        let old = v.view();
+        v.resize(screen.width, screen.height);
+        v.set_view(view);
+        v.redraw();
+        v.output(screen, &path);
+        v.set_view(old);
+        v.redraw();
+    }
+    //バッファiの表示とパス。今のファイルのパスはdir.pathにある
+    pub fn get<'a>(&'a mut self, i: usize, dir: &'a mut Dir) -> (&'a mut View, &'a mut String) {
+        let path = if i == self.current {
+            &mut dir.path
+        } else {
+            &mut self.paths[i]
+        };
+        return (&mut self.views[i], path);
+    }
+    pub fn modified(&self, i: usize) -> bool {
+        return self.views[i].modified();
+    }
+    //今のファイルを一覧から除く。最後の1つを閉じたときは無題のバッファを開く
+    pub fn close(&mut self, dir: &mut Dir) {
+        self.views.remove(self.current);
+        self.paths.remove(self.current);
+        if self.views.is_empty() {
+            let normal = Normal::new(Buffer::new());
+            self.views.push(View::Text(Box::new(normal)));
+            self.paths.push(String::new());
+        }
+        self.show(self.current.min(self.len() - 1), dir);
+    }
+    pub fn switch(&mut self, i: usize, dir: &mut Dir) {
+        self.paths[self.current] = dir.path.clone();
+        self.show(i, dir);
+    }
+    fn show(&mut self, i: usize, dir: &mut Dir) {
+        self.current = i;
+        dir.path = self.paths[i].clone();
+        self.views[i].redraw();
+        self.set_index();
+    }
+    pub fn next(&mut self, dir: &mut Dir) {
+        self.switch((self.current + 1) % self.len(), dir);
+    }
+    pub fn previous(&mut self, dir: &mut Dir) {
+        self.switch((self.current + self.len() - 1) % self.len(), dir);
+    }
+    fn set_index(&mut self) {
+        let len = self.len();
+        for (i, view) in self.views.iter_mut().enumerate() {
+            view.set_index((i + 1, len));
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::config;
+use std::io::{Stdout, Write};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+//OSC 52で送るBase64の長さの上限。多くの端末はこれより長いものを受け取らない
+const OSC52_LIMIT: usize = 100_000;
+
+//コピーした文字列を保持する。設定のosc52がtrueなら端末のクリップボードにも書き込む
+pub struct Clipboard {
+    text: String,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let text = String::new();
+        return Self { text };
+    }
+    //長すぎて端末のクリップボードに書き込めなかったときはfalseを返す
+    pub fn copy(&mut self, stdout: &mut Stdout, text: String) -> bool {
+        let mut sent = true;
+        if config::current().osc52 {
+            let encoded = base64(text.as_bytes());
+            if encoded.len() <= OSC52_LIMIT {
+                write!(stdout, "\x1b]52;c;{}\x07", encoded).unwrap();
+            } else {
+                sent = false;
+            }
+        }
+        self.text = text;
+        return sent;
+    }
+    pub fn paste(&self) -> &str {
+        return &self.text;
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    return output;
+}
@@ -0,0 +1,121 @@
+//キーに割り当てる操作。設定ファイルではnameの名前で書く
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Open,
+    Save,
+    SaveAs,
+    Quit,
+    Close,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    Find,
+    Replace,
+    ToggleLineEnding,
+    Reopen,
+    NextEncoding,
+    NextBuffer,
+    PreviousBuffer,
+    BufferList,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    NextPane,
+    Reload,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    PageUp,
+    PageDown,
+    LineStart,
+    LineEnd,
+    DeleteBackward,
+    NewLine,
+    Indent,
+    Unindent,
+    SelectNode,
+    ParentNode,
+    NextNode,
+    Select,
+    Cancel,
+    ToggleInput,
+    FocusList,
+    NextMatch,
+    PreviousMatch,
+    ToggleRegex,
+    ToggleIgnoreCase,
+    ToggleWord,
+    Discard,
+    ReplaceMatch,
+    SkipMatch,
+    ReplaceAll,
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Self::Open => "open",
+            Self::Save => "save",
+            Self::SaveAs => "save_as",
+            Self::Quit => "quit",
+            Self::Close => "close",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Find => "find",
+            Self::Replace => "replace",
+            Self::ToggleLineEnding => "toggle_line_ending",
+            Self::Reopen => "reopen",
+            Self::NextEncoding => "next_encoding",
+            Self::NextBuffer => "next_buffer",
+            Self::PreviousBuffer => "previous_buffer",
+            Self::BufferList => "buffer_list",
+            Self::SplitHorizontal => "split_horizontal",
+            Self::SplitVertical => "split_vertical",
+            Self::ClosePane => "close_pane",
+            Self::NextPane => "next_pane",
+            Self::Reload => "reload",
+            Self::MoveUp => "move_up",
+            Self::MoveDown => "move_down",
+            Self::MoveLeft => "move_left",
+            Self::MoveRight => "move_right",
+            Self::SelectUp => "select_up",
+            Self::SelectDown => "select_down",
+            Self::SelectLeft => "select_left",
+            Self::SelectRight => "select_right",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::LineStart => "line_start",
+            Self::LineEnd => "line_end",
+            Self::DeleteBackward => "delete_backward",
+            Self::NewLine => "new_line",
+            Self::Indent => "indent",
+            Self::Unindent => "unindent",
+            Self::SelectNode => "select_node",
+            Self::ParentNode => "parent_node",
+            Self::NextNode => "next_node",
+            Self::Select => "select",
+            Self::Cancel => "cancel",
+            Self::ToggleInput => "toggle_input",
+            Self::FocusList => "focus_list",
+            Self::NextMatch => "next_match",
+            Self::PreviousMatch => "previous_match",
+            Self::ToggleRegex => "toggle_regex",
+            Self::ToggleIgnoreCase => "toggle_ignore_case",
+            Self::ToggleWord => "toggle_word",
+            Self::Discard => "discard",
+            Self::ReplaceMatch => "replace_match",
+            Self::SkipMatch => "skip_match",
+            Self::ReplaceAll => "replace_all",
+        };
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{buffer::LineEnding, error::Error, file::ENCODINGS, keys::Keymaps};
+use encoding_rs::{Encoding, UTF_8};
+use std::{
+    env::var,
+    fs::read_to_string,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{LazyLock, RwLock, RwLockReadGuard},
+    time::Duration,
+};
+use toml::{Table, Value};
+
+//設定ファイルで変えられる項目
+#[derive(Clone)]
+pub struct Config {
+    //Tabキーやインデントで入れる空白の数
+    pub tab_width: usize,
+    //長い行を折り返すか。折り返さないときは横にスクロールする
+    pub wrap: bool,
+    pub line_numbers: bool,
+    //darkかlight、themesディレクトリにあるテーマの名前、またはテーマファイルのパス
+    pub theme: String,
+    //新しく作るファイルの文字コードと改行コード
+    pub encoding: &'static Encoding,
+    pub line_ending: LineEnding,
+    //変更済みのファイルを自動で保存する間隔。Noneなら自動では保存しない
+    pub autosave: Option<Duration>,
+    //コピーした文字列をOSC 52で端末のクリップボードにも書き込むか
+    pub osc52: bool,
+    //モードごとのキーの割り当て
+    pub keymaps: Keymaps,
+}
+
+impl Config {
+    fn new() -> Self {
+        let tab_width = 4;
+        let wrap = true;
+        let line_numbers = true;
+        let theme = String::from("dark");
+        let encoding = UTF_8;
+        let line_ending = LineEnding::Lf;
+        let autosave = None;
+        let osc52 = true;
+        let keymaps = Keymaps::new();
+        return Self {
+            tab_width,
+            wrap,
+            line_numbers,
+            theme,
+            encoding,
+            line_ending,
+            autosave,
+            osc52,
+            keymaps,
+        };
+    }
+    //テーマの名前をtheme::loadに渡す名前にする
+    pub fn theme_path(&self) -> String {
+        let path = config_dir()
+            .join("themes")
+            .join(format!("{}.toml", self.theme));
+        if path.exists() {
+            return path.to_string_lossy().to_string();
+        }
+        return self.theme.clone();
+    }
+}
+
+//今の設定。読み直すまでは起動したときに読んだものを使う
+static CONFIG: LazyLock<RwLock<Config>> = LazyLock::new(|| RwLock::new(Config::new()));
+
+pub fn current() -> RwLockReadGuard<'static, Config> {
+    return CONFIG.read().unwrap();
+}
+
+pub fn set(config: Config) {
+    *CONFIG.write().unwrap() = config;
+}
+
+//設定ファイルを置くディレクトリ。XDG_CONFIG_HOMEがなければ~/.config
+pub fn config_dir() -> PathBuf {
+    let base = match var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(var("HOME").unwrap_or_default()).join(".config"),
+    };
+    return base.join("editor1");
+}
+
+//config.tomlを読む。ファイルがなければ初期値を使い、項目の名前や値が正しくなければエラーにする
+pub fn load() -> Result<Config, Error> {
+    let path = config_dir().join("config.toml");
+    let name = path.to_string_lossy().to_string();
+    let error = |msg: String| Error::Config(name.clone(), msg);
+    let mut config = Config::new();
+    let text = match read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(config),
+        Err(e) => return Err(error(e.to_string())),
+    };
+    let table = text
+        .parse::<Table>()
+        .map_err(|e| error(e.message().to_string()))?;
+    for (key, value) in &table {
+        match key.as_str() {
+            "tab_width" => {
+                config.tab_width = match integer(key, value).map_err(error)? {
+                    n @ 1..=16 => n as usize,
+                    _ => return Err(error(String::from("tab_widthは1から16です"))),
+                }
+            }
+            "wrap" => config.wrap = boolean(key, value).map_err(error)?,
+            "line_numbers" => config.line_numbers = boolean(key, value).map_err(error)?,
+            "theme" => config.theme = string(key, value).map_err(error)?.to_string(),
+            "encoding" => {
+                let label = string(key, value).map_err(error)?;
+                config.encoding = Encoding::for_label(label.as_bytes())
+                    .filter(|e| ENCODINGS.contains(e))
+                    .ok_or_else(|| error(format!("文字コード{}は使えません", label)))?;
+            }
+            "line_ending" => {
+                config.line_ending = match string(key, value).map_err(error)? {
+                    "lf" | "LF" => LineEnding::Lf,
+                    "crlf" | "CRLF" => LineEnding::Crlf,
+                    _ => return Err(error(String::from("line_endingはlfかcrlfです"))),
+                }
+            }
+            "autosave" => {
+                config.autosave = match integer(key, value).map_err(error)? {
+                    0 => None,
+                    n if 0 < n => Some(Duration::from_secs(n as u64)),
+                    _ => return Err(error(String::from("autosaveは0以上の秒数です"))),
+                }
+            }
+            "osc52" => config.osc52 = boolean(key, value).map_err(error)?,
+            //[keys.normal]のようにモードごとの表に"Ctrl+K Ctrl+S" = "save"と書く
+            "keys" => {
+                let Some(modes) = value.as_table() else {
+                    return Err(error(String::from("keysは表です")));
+                };
+                for (mode, bindings) in modes {
+                    let Some(keymap) = config.keymaps.get_mut(mode) else {
+                        return Err(error(format!("{}というモードはありません", mode)));
+                    };
+                    let Some(bindings) = bindings.as_table() else {
+                        return Err(error(format!("keys.{}は表です", mode)));
+                    };
+                    for (chord, command) in bindings {
+                        let command = string(chord, command).map_err(error)?;
+                        keymap.bind(chord, command).map_err(error)?;
+                    }
+                }
+            }
+            _ => return Err(error(format!("{}という項目はありません", key))),
+        }
+    }
+    return Ok(config);
+}
+
+fn integer(key: &str, value: &Value) -> Result<i64, String> {
+    return value
+        .as_integer()
+        .ok_or_else(|| format!("{}は整数です", key));
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    return value
+        .as_bool()
+        .ok_or_else(|| format!("{}はtrueかfalseです", key));
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    return value.as_str().ok_or_else(|| format!("{}は文字列です", key));
+}
@@ -0,0 +1,74 @@
+use crate::{
+    buffers::View,
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    screen::Screen,
+    screen::Style,
+    Dir,
+};
+use crossterm::event::Event;
+use std::io::Stdout;
+use unicode_width::UnicodeWidthStr;
+
+//確認の後に続ける操作
+pub enum Pending {
+    Quit,
+    Close,
+}
+
+pub enum Answer {
+    Save,
+    Discard,
+    Cancel,
+}
+
+//変更済みのファイルを閉じる前に、保存するか破棄するかを聞く
+pub struct Confirm {
+    chord: Chord,
+}
+
+impl Confirm {
+    pub fn new() -> Self {
+        let chord = Chord::new();
+        return Self { chord };
+    }
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        screen: &mut Screen,
+        view: &mut View,
+        dir: &Dir,
+    ) -> Option<Answer> {
+        self.output(stdout, screen, view, dir);
+        return self.input();
+    }
+    fn output(&mut self, stdout: &mut Stdout, screen: &mut Screen, view: &mut View, dir: &Dir) {
+        view.output(screen, &dir.path);
+        let path = if dir.path.is_empty() {
+            "無題"
+        } else {
+            &dir.path
+        };
+        let prompt = format!("{}の変更を保存しますか？(y/n/c) ", path);
+        let y = screen.height.saturating_sub(1);
+        screen.clear_line(0, y);
+        let x = screen.put(0, y, &prompt, Style::new());
+        screen.put(x, y, &self.chord.pending(), Style::new());
+        let x = (prompt.width() as u16).min(screen.width.saturating_sub(1));
+        screen.set_cursor(x, y);
+        screen.flush(stdout);
+    }
+    fn input(&mut self) -> Option<Answer> {
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let input = self.chord.input(&config::current().keymaps.confirm, key);
+            return match input {
+                Input::Command(Command::Save) => Some(Answer::Save),
+                Input::Command(Command::Discard) => Some(Answer::Discard),
+                Input::Command(Command::Cancel) => Some(Answer::Cancel),
+                _ => None,
+            };
+        }
+        return None;
+    }
+}
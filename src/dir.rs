@@ -1,4 +1,4 @@
-use crate::Cursor;
+use crate::{theme, Cursor};
 use crossterm::{
     cursor::MoveTo,
     style::{Attribute, Color, SetAttribute, SetForegroundColor},
@@ -9,12 +9,6 @@ use std::{
     path::{absolute, PathBuf},
 };
 
-const FOREGROUND: SetForegroundColor = SetForegroundColor(Color::Rgb {
-    r: 135,
-    g: 175,
-    b: 175,
-});
-
 pub struct Dir {
     pub path: String,
     pub dir_path: PathBuf,
@@ -29,9 +23,9 @@ impl Dir {
         let dir_path = absolute(".").unwrap();
         let mut dirs = Vec::new();
         let mut files = Vec::new();
-        for i in read_dir(&dir_path).unwrap() {
-            let f = i.unwrap();
-            if f.file_type().unwrap().is_dir() {
+        //読めないディレクトリや項目は飛ばす
+        for f in read_dir(&dir_path).into_iter().flatten().flatten() {
+            if f.file_type().is_ok_and(|t| t.is_dir()) {
                 dirs.push(f);
             } else {
                 files.push(f);
@@ -51,9 +45,8 @@ impl Dir {
     pub fn set(&mut self) {
         self.dirs.clear();
         self.files.clear();
-        for i in read_dir(&self.dir_path).unwrap() {
-            let f = i.unwrap();
-            if f.file_type().unwrap().is_dir() {
+        for f in read_dir(&self.dir_path).into_iter().flatten().flatten() {
+            if f.file_type().is_ok_and(|t| t.is_dir()) {
                 self.dirs.push(f);
             } else {
                 self.files.push(f);
@@ -96,7 +89,7 @@ impl Dir {
             "{}{}{}{}{}{}",
             MoveTo(0, y),
             SetAttribute(Attribute::Underlined),
-            FOREGROUND,
+            SetForegroundColor(theme::current().directory),
             pathname,
             SetForegroundColor(Color::Reset),
             SetAttribute(Attribute::NoUnderline)
@@ -108,7 +101,7 @@ impl Dir {
             stdout,
             "{}{}{}{}",
             MoveTo(0, y),
-            FOREGROUND,
+            SetForegroundColor(theme::current().directory),
             pathname,
             SetForegroundColor(Color::Reset)
         )
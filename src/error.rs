@@ -0,0 +1,32 @@
+use encoding_rs::Encoding;
+use std::{
+    fmt::{self, Display},
+    io,
+};
+
+//ファイルの読み書きや設定の読み込みで起きたエラー。ステータス行に表示して編集を続ける
+pub enum Error {
+    Open(String, io::Error),
+    Read(String, io::Error),
+    Save(String, io::Error),
+    Decode(String, &'static Encoding),
+    //テーマの名前と理由
+    Theme(String, String),
+    //設定ファイルのパスと理由
+    Config(String, String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::Open(path, e) => write!(f, "{}を開けません: {}", path, e),
+            Self::Read(path, e) => write!(f, "{}を読み込めません: {}", path, e),
+            Self::Save(path, e) => write!(f, "{}に保存できません: {}", path, e),
+            Self::Decode(path, encoding) => {
+                write!(f, "{}を{}として読めません", path, encoding.name())
+            }
+            Self::Theme(name, msg) => write!(f, "テーマ{}を読み込めません: {}", name, msg),
+            Self::Config(path, msg) => write!(f, "設定{}を読み込めません: {}", path, msg),
+        };
+    }
+}
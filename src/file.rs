@@ -0,0 +1,269 @@
+use crate::{
+    buffer::{Buffer, LineEnding},
+    error::Error,
+};
+use encoding_rs::{Encoding, EUC_JP, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8};
+use std::{
+    fs::{canonicalize, create_dir_all, metadata, remove_file, rename, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+//読み込んだファイルの内容。テキストとして読めないものはバイト列のまま持つ
+pub enum Content {
+    Text(Buffer),
+    Binary(Vec<u8>),
+}
+
+//開き直すときに順に試す文字コード
+pub const ENCODINGS: [&Encoding; 5] = [UTF_8, UTF_16LE, UTF_16BE, SHIFT_JIS, EUC_JP];
+
+//文字コードを判別して読み込む
+pub fn read(path: &str) -> Result<Content, Error> {
+    let b = read_bytes(path)?;
+    return match detect(&b) {
+        Some((text, encoding, bom)) => Ok(Content::Text(load(&text, encoding, bom))),
+        None => Ok(Content::Binary(b)),
+    };
+}
+
+//encodingとして読み込む。BOMがあれば取り除く
+pub fn read_as(path: &str, encoding: &'static Encoding) -> Result<Buffer, Error> {
+    let b = read_bytes(path)?;
+    let (bom, b) = match Encoding::for_bom(&b) {
+        Some((e, l)) if e == encoding => (true, &b[l..]),
+        _ => (false, &b[..]),
+    };
+    return match encoding.decode_without_bom_handling_and_without_replacement(b) {
+        Some(text) => Ok(load(&text, encoding, bom)),
+        None => Err(Error::Decode(path.to_string(), encoding)),
+    };
+}
+
+//pathを読める文字コード。UTF-16はBOMがあるときだけ候補にする
+pub fn encodings(path: &str) -> Result<Vec<&'static Encoding>, Error> {
+    let b = read_bytes(path)?;
+    let bom = Encoding::for_bom(&b);
+    let encodings = ENCODINGS.into_iter().filter(|&encoding| {
+        let b = match bom {
+            Some((e, l)) if e == encoding => &b[l..],
+            _ if encoding == UTF_16LE || encoding == UTF_16BE => return false,
+            _ => &b[..],
+        };
+        return encoding
+            .decode_without_bom_handling_and_without_replacement(b)
+            .is_some();
+    });
+    return Ok(encodings.collect());
+}
+
+fn read_bytes(path: &str) -> Result<Vec<u8>, Error> {
+    let file = File::open(path).map_err(|e| Error::Open(path.to_string(), e))?;
+    let mut reader = BufReader::new(file);
+    let mut b = Vec::new();
+    reader
+        .read_to_end(&mut b)
+        .map_err(|e| Error::Read(path.to_string(), e))?;
+    return Ok(b);
+}
+
+//BOMがあればそれに従い、なければUTF-8、Shift_JIS、EUC-JPの順に試す
+//Shift_JISとEUC-JPの両方で読めるときは、かなと漢字が多くなる方を選ぶ
+//BOMがなくNULを含むものはバイナリとして扱う
+fn detect(b: &[u8]) -> Option<(String, &'static Encoding, bool)> {
+    if let Some((encoding, l)) = Encoding::for_bom(b) {
+        let text = encoding.decode_without_bom_handling_and_without_replacement(&b[l..])?;
+        return Some((text.into_owned(), encoding, true));
+    }
+    if b.contains(&0) {
+        return None;
+    }
+    if let Ok(text) = std::str::from_utf8(b) {
+        return Some((text.to_string(), UTF_8, false));
+    }
+    return [SHIFT_JIS, EUC_JP]
+        .into_iter()
+        .filter_map(|encoding| {
+            let text = encoding.decode_without_bom_handling_and_without_replacement(b)?;
+            Some((text.into_owned(), encoding, false))
+        })
+        .max_by_key(|(text, encoding, _)| (japanese(text), *encoding == SHIFT_JIS));
+}
+
+fn japanese(text: &str) -> usize {
+    return text
+        .chars()
+        .filter(|c| matches!(c, '\u{3040}'..='\u{30ff}' | '\u{4e00}'..='\u{9fff}'))
+        .count();
+}
+
+//読み込んだ文字列をBufferにする。末尾の改行は最後の行の終わりとして扱う
+//改行の形式、末尾の改行の有無を覚えておく。形式が混在していれば\rを取り除かずに持つ
+fn load(b: &str, encoding: &'static Encoding, bom: bool) -> Buffer {
+    let crlf = b.matches("\r\n").count();
+    let line_ending = match b.matches('\n').count() {
+        n if crlf == 0 || n == 0 => LineEnding::Lf,
+        n if crlf == n => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    };
+    let final_newline = b.ends_with('\n');
+    let b = if line_ending == LineEnding::Crlf {
+        b.replace("\r\n", "\n")
+    } else {
+        b.to_string()
+    };
+    let mut buffer = Buffer::from_str(b.strip_suffix('\n').unwrap_or(&b));
+    buffer.line_ending = line_ending;
+    buffer.encoding = encoding;
+    buffer.bom = bom;
+    buffer.final_newline = final_newline;
+    return buffer;
+}
+
+//createなら無いディレクトリを作ってから保存する
+pub fn save(buffer: &Buffer, path: &str, create: bool) -> Result<(), Error> {
+    return save_with(path, create, |writer| buffer.write_to(writer));
+}
+
+//バイト列をそのまま保存する
+pub fn save_bytes(bytes: &[u8], path: &str) -> Result<(), Error> {
+    return save_with(path, false, |writer| writer.write_all(bytes));
+}
+
+//同じディレクトリの一時ファイルに書いてfsyncし、元のファイルの権限と所有者を移してから置き換える
+fn save_with<F>(path: &str, create: bool, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let error = |e| Error::Save(path.to_string(), e);
+    if let Some(parent) = Path::new(path).parent().filter(|_| create) {
+        create_dir_all(parent).map_err(error)?;
+    }
+    //シンボリックリンクはリンク先を置き換える
+    let target = canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = target.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+    let result = write_temp(&temp, &target, write).and_then(|()| rename(&temp, &target));
+    if let Err(e) = result {
+        let _ = remove_file(&temp);
+        return Err(error(e));
+    }
+    //名前の変更もディスクに残るようにディレクトリをfsyncする
+    #[cfg(unix)]
+    if let Some(parent) = target.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    return Ok(());
+}
+
+fn write_temp<F>(temp: &Path, target: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let file = OpenOptions::new().write(true).create_new(true).open(temp)?;
+    if let Ok(metadata) = metadata(target) {
+        //所有者を変えられるのは権限があるときだけなので失敗は無視する
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{fchown, MetadataExt};
+            let _ = fchown(&file, Some(metadata.uid()), Some(metadata.gid()));
+        }
+        file.set_permissions(metadata.permissions())?;
+    }
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //判別して読み込んだものを書き出す
+    fn round_trip(b: &[u8]) -> Vec<u8> {
+        let (text, encoding, bom) = detect(b).unwrap();
+        let mut output = Vec::new();
+        load(&text, encoding, bom).write_to(&mut output).unwrap();
+        return output;
+    }
+
+    #[test]
+    fn line_endings() {
+        for b in [
+            &b"a\nb\n"[..],
+            b"a\r\nb\r\n",
+            b"a\r\nb\nc\r\n",
+            b"a\nb",
+            b"a\r\nb",
+            b"a\r\nb\nc\r",
+            b"",
+            b"\n",
+        ] {
+            assert_eq!(round_trip(b), b);
+        }
+    }
+
+    #[test]
+    fn mixed() {
+        let buffer = load("a\r\nb\nc\r\n", UTF_8, false);
+        assert!(buffer.line_ending == LineEnding::Mixed);
+        assert_eq!(buffer.line(0), "a\r");
+        assert_eq!(buffer.line(1), "b");
+        let buffer = load("a\r\nb\r\n", UTF_8, false);
+        assert!(buffer.line_ending == LineEnding::Crlf);
+        assert_eq!(buffer.line(0), "a");
+    }
+
+    #[test]
+    fn utf8_bom() {
+        let b = b"\xef\xbb\xbfa\r\nb";
+        assert_eq!(round_trip(b), b);
+    }
+
+    #[test]
+    fn utf16_bom() {
+        let le = b"\xff\xfea\x00\r\x00\n\x00\x42\x30\n\x00";
+        assert_eq!(round_trip(le), le);
+        let be = b"\xfe\xff\x00a\x00\n\x30\x42";
+        assert_eq!(round_trip(be), be);
+        assert!(detect(le).unwrap().1 == UTF_16LE);
+        assert!(detect(be).unwrap().1 == UTF_16BE);
+    }
+
+    #[test]
+    fn japanese_encodings() {
+        //「日本語のテキスト」
+        let sjis = b"\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67\r\n";
+        let euc = b"\xc6\xfc\xcb\xdc\xb8\xec\xa4\xce\xa5\xc6\xa5\xad\xa5\xb9\xa5\xc8\n";
+        assert!(detect(sjis).unwrap().1 == SHIFT_JIS);
+        assert!(detect(euc).unwrap().1 == EUC_JP);
+        assert_eq!(round_trip(sjis), sjis);
+        assert_eq!(round_trip(euc), euc);
+    }
+
+    #[test]
+    fn candidates() {
+        let path = std::env::temp_dir().join(format!("editor1-{}.txt", process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"abc\n").unwrap();
+        assert!(encodings(path).ok().unwrap() == [UTF_8, SHIFT_JIS, EUC_JP]);
+        std::fs::write(path, b"\xff\xfea\x00").unwrap();
+        assert!(encodings(path).ok().unwrap() == [UTF_16LE]);
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn binary() {
+        assert!(detect(b"a\x00b").is_none());
+    }
+}
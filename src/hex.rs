@@ -0,0 +1,292 @@
+use crate::{
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    normal::click,
+    screen::{Screen, Style},
+    theme,
+    windows::Split,
+    Action, Cursor, Dir, Mode,
+};
+use crossterm::{
+    cursor::Hide,
+    event::{Event, KeyCode, KeyModifiers},
+};
+use std::{
+    io::{Stdout, Write},
+    time::Instant,
+};
+
+//1行に表示するバイト数
+const COLUMNS: usize = 16;
+
+//テキストとして読めないファイルをバイト単位で表示、上書きする
+pub struct Hex {
+    pub bytes: Vec<u8>,
+    cursor: usize,
+    //カーソルのあるバイトの下位4ビットを入力する状態か
+    low: bool,
+    offset: usize,
+    width: u16,
+    height: u16,
+    modified: bool,
+    pub msg: String,
+    pub index: (usize, usize),
+    chord: Chord,
+}
+
+impl Hex {
+    pub fn new() -> Self {
+        let bytes = Vec::new();
+        let cursor = 0;
+        let low = false;
+        let offset = 0;
+        let width = 0;
+        let height = 0;
+        let modified = false;
+        let msg = String::new();
+        let index = (1, 1);
+        let chord = Chord::new();
+        return Self {
+            bytes,
+            cursor,
+            low,
+            offset,
+            width,
+            height,
+            modified,
+            msg,
+            index,
+            chord,
+        };
+    }
+    pub fn set_bytes(&mut self, bytes: Vec<u8>) {
+        self.bytes = bytes;
+        self.cursor = 0;
+        self.low = false;
+        self.offset = 0;
+        self.modified = false;
+    }
+    pub fn update(&mut self) {
+        self.modified = false;
+    }
+    pub fn modified(&self) -> bool {
+        return self.modified;
+    }
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        screen: &mut Screen,
+        mode: &mut Mode,
+        dir: &Dir,
+        deadline: Option<Instant>,
+    ) -> (String, Action) {
+        self.output(screen, &dir.path);
+        screen.flush(stdout);
+        return self.input(stdout, mode, dir, deadline);
+    }
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+    }
+    //カーソルのバイト位置はxに入れる
+    pub fn view(&self) -> (Cursor, usize) {
+        let mut cursor = Cursor::new();
+        cursor.x = self.cursor;
+        return (cursor, self.offset);
+    }
+    pub fn set_view(&mut self, view: (Cursor, usize)) {
+        self.move_to(view.0.x);
+        self.offset = view.1.min(self.cursor / COLUMNS);
+    }
+    pub fn output(&mut self, screen: &mut Screen, path: &str) {
+        self.scroll();
+        let rows = self.height.saturating_sub(1);
+        for y in 0..rows {
+            screen.clear_line(0, y);
+            let start = (self.offset + y as usize) * COLUMNS;
+            if self.bytes.len() <= start && 0 < start {
+                continue;
+            }
+            let end = (start + COLUMNS).min(self.bytes.len());
+            let gray = Style::fg(theme::current().gutter);
+            let x = screen.put(0, y, &format!("{:08x}  ", start), gray);
+            for i in start..end {
+                let style = self.style(i);
+                let x = x + column(i - start);
+                screen.put(x, y, &format!("{:02x}", self.bytes[i]), style);
+            }
+            let x = x + column(COLUMNS) + 1;
+            for i in start..end {
+                let b = self.bytes[i];
+                let c = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                let style = self.style(i);
+                screen.put(x + (i - start) as u16, y, &c.to_string(), style);
+            }
+        }
+        self.output_msg(screen, path);
+        let y = (self.cursor / COLUMNS - self.offset) as u16;
+        let x = 10 + column(self.cursor % COLUMNS) + self.low as u16;
+        screen.set_cursor(x, y);
+    }
+    fn style(&self, i: usize) -> Style {
+        let mut style = Style::new();
+        style.reverse = i == self.cursor;
+        return style;
+    }
+    fn output_msg(&self, screen: &mut Screen, path: &str) {
+        let msg = if self.modified { "変更済み" } else { "" };
+        let y = self.height.saturating_sub(1);
+        screen.clear_line(0, y);
+        screen.put(
+            0,
+            y,
+            &format!(
+                "[{}/{}][{}]{} {}",
+                self.index.0, self.index.1, path, msg, self.msg
+            ),
+            Style::new(),
+        );
+        let position = format!("HEX {:08x}/{:08x}", self.cursor, self.bytes.len());
+        let x = self.width.saturating_sub(position.len() as u16);
+        screen.put(x, y, &position, Style::new());
+    }
+    fn input(
+        &mut self,
+        stdout: &mut Stdout,
+        mode: &mut Mode,
+        dir: &Dir,
+        deadline: Option<Instant>,
+    ) -> (String, Action) {
+        let mut path = String::new();
+        let mut action = Action::None;
+        let event = keys::read(deadline);
+        if let Ok(Event::Mouse(mouse)) = event {
+            action = click(mouse);
+        }
+        if let Ok(Event::Key(key)) = event {
+            self.msg.clear();
+            let input = self.chord.input(&config::current().keymaps.hex, key);
+            match input {
+                Input::Command(command) => {
+                    (path, action) = self.execute(command, stdout, mode, dir);
+                }
+                Input::Pending => {
+                    self.msg = self.chord.pending();
+                }
+                Input::Unbound(keys) => {
+                    self.msg = format!("{}は割り当てられていません", keys);
+                }
+                //CtrlやAltと一緒に押した文字は入力しない
+                Input::Key(key) => {
+                    let plain = key.modifiers != KeyModifiers::CONTROL
+                        && key.modifiers != KeyModifiers::ALT;
+                    if let (KeyCode::Char(c), true) = (key.code, plain) {
+                        if let Some(n) = c.to_digit(16) {
+                            self.overwrite(n as u8);
+                        }
+                    }
+                }
+            }
+        }
+        return (path, action);
+    }
+    //キーに割り当てたコマンドを実行する
+    fn execute(
+        &mut self,
+        command: Command,
+        stdout: &mut Stdout,
+        mode: &mut Mode,
+        dir: &Dir,
+    ) -> (String, Action) {
+        let mut path = String::new();
+        let mut action = Action::None;
+        let page = self.height.saturating_sub(1).max(1) as usize * COLUMNS;
+        match command {
+            Command::Open => {
+                write!(stdout, "{}", Hide).unwrap();
+                *mode = Mode::Open;
+            }
+            Command::Save => {
+                path = dir.path.clone();
+            }
+            Command::Quit => {
+                action = Action::Quit;
+            }
+            Command::Close => {
+                action = Action::Close;
+            }
+            Command::NextBuffer => {
+                action = Action::Next;
+            }
+            Command::PreviousBuffer => {
+                action = Action::Previous;
+            }
+            Command::BufferList => {
+                write!(stdout, "{}", Hide).unwrap();
+                *mode = Mode::List;
+            }
+            Command::SplitHorizontal => {
+                action = Action::Split(Split::Horizontal);
+            }
+            Command::SplitVertical => {
+                action = Action::Split(Split::Vertical);
+            }
+            Command::ClosePane => {
+                action = Action::ClosePane;
+            }
+            Command::NextPane => {
+                action = Action::NextPane;
+            }
+            Command::Reload => {
+                action = Action::Reload;
+            }
+            Command::MoveUp => self.move_to(self.cursor.saturating_sub(COLUMNS)),
+            Command::MoveDown => self.move_to(self.cursor + COLUMNS),
+            Command::MoveLeft => self.move_to(self.cursor.saturating_sub(1)),
+            Command::MoveRight => self.move_to(self.cursor + 1),
+            Command::PageUp => self.move_to(self.cursor.saturating_sub(page)),
+            Command::PageDown => self.move_to(self.cursor + page),
+            Command::LineStart => self.move_to(self.cursor - self.cursor % COLUMNS),
+            Command::LineEnd => self.move_to(self.cursor - self.cursor % COLUMNS + COLUMNS - 1),
+            _ => {}
+        }
+        return (path, action);
+    }
+    //上位4ビット、下位4ビットの順に書き換え、下位を書いたら次のバイトへ進む
+    fn overwrite(&mut self, n: u8) {
+        let Some(b) = self.bytes.get_mut(self.cursor) else {
+            return;
+        };
+        if self.low {
+            *b = *b & 0xf0 | n;
+            self.move_to(self.cursor + 1);
+        } else {
+            *b = *b & 0x0f | n << 4;
+            self.low = true;
+        }
+        self.modified = true;
+    }
+    fn move_to(&mut self, i: usize) {
+        self.cursor = i.min(self.bytes.len().saturating_sub(1));
+        self.low = false;
+    }
+    fn scroll(&mut self) {
+        let rows = self.height.saturating_sub(1).max(1) as usize;
+        let y = self.cursor / COLUMNS;
+        if y < self.offset {
+            self.offset = y;
+        } else if self.offset + rows <= y {
+            self.offset = y + 1 - rows;
+        }
+    }
+}
+
+//16進の列でi番目のバイトが始まる位置。8バイトごとに空白を1つ空ける
+fn column(i: usize) -> u16 {
+    return (i * 3 + i / 8) as u16;
+}
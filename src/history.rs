@@ -0,0 +1,226 @@
+use crate::{buffer::LineEnding, Cursor};
+
+//(y, x)の位置でremovedをinsertedに置き換えた編集。xは行内のバイト位置
+#[derive(Clone)]
+pub struct Change {
+    pub y: usize,
+    pub x: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Typing,
+    Delete,
+    Edit,
+}
+
+//1回のundo/redoで戻す単位。cursorは編集前のカーソル位置
+//line_endingは一緒に改行コードを(前, 後)に変えたとき
+#[derive(Clone)]
+pub struct Step {
+    pub changes: Vec<Change>,
+    pub cursor: Cursor,
+    pub line_ending: Option<(LineEnding, LineEnding)>,
+    kind: Kind,
+    id: usize,
+}
+
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    open: bool,
+    group: usize,
+    //beginしたがまだ何も編集していないまとまりの、編集前のカーソル位置
+    pending: Option<Cursor>,
+    next: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        let undo = Vec::new();
+        let redo = Vec::new();
+        let open = false;
+        let group = 0;
+        let pending = None;
+        let next = 1;
+        return Self {
+            undo,
+            redo,
+            open,
+            group,
+            pending,
+            next,
+        };
+    }
+    pub fn push(&mut self, change: Change, cursor: &Cursor, kind: Kind) {
+        self.step(cursor, kind);
+        self.undo.last_mut().unwrap().changes.push(change);
+        self.open = true;
+    }
+    //changeを記録するステップを用意する。まとまりの最初の編集か、前のステップに続けられなければ新しく作る
+    fn step(&mut self, cursor: &Cursor, kind: Kind) {
+        self.redo.clear();
+        let cursor = match self.pending.take() {
+            Some(cursor) => cursor,
+            None => {
+                let merge = match self.undo.last() {
+                    Some(step) => {
+                        0 < self.group || (self.open && step.kind == kind && kind != Kind::Edit)
+                    }
+                    None => false,
+                };
+                if merge {
+                    return;
+                }
+                cursor.clone()
+            }
+        };
+        self.undo.push(Step {
+            changes: Vec::new(),
+            cursor,
+            line_ending: None,
+            kind,
+            id: self.next,
+        });
+        self.next += 1;
+    }
+    //endが呼ばれるまでの編集を1つのステップにまとめる
+    //ステップは最初の編集で作るので、何も編集しなければ履歴もredoも変わらない
+    pub fn begin(&mut self, cursor: &Cursor) {
+        if self.group == 0 {
+            self.pending = Some(cursor.clone());
+        }
+        self.group += 1;
+    }
+    pub fn end(&mut self) {
+        self.group -= 1;
+        if self.group == 0 {
+            self.pending = None;
+            self.seal();
+        }
+    }
+    //beginとendの間で改行コードをbeforeからafterに変えたことを記録する
+    pub fn set_line_ending(&mut self, cursor: &Cursor, before: LineEnding, after: LineEnding) {
+        if self.group == 0 {
+            return;
+        }
+        self.step(cursor, Kind::Edit);
+        self.undo.last_mut().unwrap().line_ending = Some((before, after));
+    }
+    //次の編集を新しいステップとして記録させる
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+    //現在の内容を表す番号。内容が同じなら同じ番号になる
+    pub fn revision(&self) -> usize {
+        return match self.undo.last() {
+            Some(step) => step.id,
+            None => 0,
+        };
+    }
+    pub fn undo(&mut self) -> Option<Step> {
+        self.seal();
+        let step = self.undo.pop()?;
+        self.redo.push(step.clone());
+        return Some(step);
+    }
+    pub fn redo(&mut self) -> Option<Step> {
+        self.seal();
+        let step = self.redo.pop()?;
+        self.undo.push(step.clone());
+        return Some(step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(x: usize, inserted: &str) -> Change {
+        return Change {
+            y: 0,
+            x,
+            removed: String::new(),
+            inserted: inserted.to_string(),
+        };
+    }
+
+    #[test]
+    fn typing() {
+        let mut history = History::new();
+        let cursor = Cursor::new();
+        history.push(change(0, "a"), &cursor, Kind::Typing);
+        history.push(change(1, "b"), &cursor, Kind::Typing);
+        history.push(change(2, "c"), &cursor, Kind::Typing);
+        assert_eq!(history.undo().unwrap().changes.len(), 3);
+        assert!(history.undo().is_none());
+        //sealした後や種類が変わったときは別のステップにする
+        history.push(change(0, "a"), &cursor, Kind::Typing);
+        history.seal();
+        history.push(change(1, "b"), &cursor, Kind::Typing);
+        history.push(change(2, "c"), &cursor, Kind::Delete);
+        history.push(change(3, "d"), &cursor, Kind::Edit);
+        history.push(change(4, "e"), &cursor, Kind::Edit);
+        assert_eq!(history.undo.len(), 5);
+    }
+
+    #[test]
+    fn group() {
+        let mut history = History::new();
+        let cursor = Cursor::new();
+        history.begin(&cursor);
+        history.push(change(0, "a"), &cursor, Kind::Edit);
+        history.begin(&cursor);
+        history.push(change(1, "b"), &cursor, Kind::Typing);
+        history.end();
+        history.push(change(2, "c"), &cursor, Kind::Delete);
+        history.end();
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo().unwrap().changes.len(), 3);
+        //何も編集しなかったまとまりは残さず、redoも消さない
+        history.begin(&cursor);
+        assert_eq!(history.revision(), 0);
+        history.end();
+        assert!(history.undo.is_empty());
+        assert!(history.redo().is_some());
+    }
+
+    #[test]
+    fn line_ending() {
+        let mut history = History::new();
+        let cursor = Cursor::new();
+        history.begin(&cursor);
+        history.set_line_ending(&cursor, LineEnding::Mixed, LineEnding::Lf);
+        history.push(change(0, "a"), &cursor, Kind::Edit);
+        history.end();
+        let step = history.undo().unwrap();
+        assert!(step.line_ending == Some((LineEnding::Mixed, LineEnding::Lf)));
+        assert!(history.redo().unwrap().line_ending.is_some());
+    }
+
+    #[test]
+    fn revision() {
+        let mut history = History::new();
+        let cursor = Cursor::new();
+        assert_eq!(history.revision(), 0);
+        history.push(change(0, "a"), &cursor, Kind::Edit);
+        let first = history.revision();
+        history.push(change(1, "b"), &cursor, Kind::Edit);
+        let second = history.revision();
+        assert_ne!(first, second);
+        history.undo();
+        assert_eq!(history.revision(), first);
+        history.undo();
+        assert_eq!(history.revision(), 0);
+        history.redo();
+        history.redo();
+        assert_eq!(history.revision(), second);
+        //戻してから別の編集をすると、前と同じ番号にはならない
+        history.undo();
+        history.push(change(1, "c"), &cursor, Kind::Edit);
+        assert_ne!(history.revision(), second);
+        assert!(history.redo().is_none());
+    }
+}
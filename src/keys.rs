@@ -0,0 +1,480 @@
+use crate::command::Command;
+use crossterm::event::{poll, read as read_event, Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    io::{self, ErrorKind},
+    mem::take,
+    time::Instant,
+};
+
+//モードごとに使えるコマンドと、初めから割り当てておくキー
+const NORMAL: &[(Command, &[&str])] = &[
+    (Command::Open, &["Ctrl+O"]),
+    (Command::Save, &["Ctrl+S"]),
+    (Command::SaveAs, &["Ctrl+A"]),
+    (Command::Quit, &["Ctrl+Q"]),
+    (Command::Close, &["Ctrl+W"]),
+    (Command::Copy, &["Ctrl+C"]),
+    (Command::Cut, &["Ctrl+X"]),
+    (Command::Paste, &["Ctrl+V"]),
+    (Command::Undo, &["Ctrl+Z"]),
+    (Command::Redo, &["Ctrl+Y"]),
+    (Command::Find, &["Ctrl+F"]),
+    (Command::Replace, &["Ctrl+R"]),
+    (Command::ToggleLineEnding, &["Ctrl+L"]),
+    (Command::Reopen, &["Alt+E"]),
+    (Command::NextEncoding, &["Alt+S"]),
+    (Command::NextBuffer, &["Alt+N", "Ctrl+PageDown"]),
+    (Command::PreviousBuffer, &["Alt+P", "Ctrl+PageUp"]),
+    (Command::BufferList, &["Alt+B"]),
+    (Command::SplitHorizontal, &["Alt+H"]),
+    (Command::SplitVertical, &["Alt+V"]),
+    (Command::ClosePane, &["Alt+X"]),
+    (Command::NextPane, &["Alt+O"]),
+    (Command::Reload, &["Alt+R"]),
+    (Command::MoveUp, &["Up"]),
+    (Command::MoveDown, &["Down"]),
+    (Command::MoveLeft, &["Left"]),
+    (Command::MoveRight, &["Right"]),
+    (Command::SelectUp, &["Shift+Up"]),
+    (Command::SelectDown, &["Shift+Down"]),
+    (Command::SelectLeft, &["Shift+Left"]),
+    (Command::SelectRight, &["Shift+Right"]),
+    (Command::DeleteBackward, &["Backspace"]),
+    (Command::NewLine, &["Enter"]),
+    (Command::Indent, &["Tab"]),
+    (Command::Unindent, &["BackTab"]),
+    //構文木を使えないときは初めからは割り当てない
+    #[cfg(feature = "tree-sitter")]
+    (Command::SelectNode, &["Alt+Up"]),
+    #[cfg(feature = "tree-sitter")]
+    (Command::ParentNode, &["Alt+Left"]),
+    #[cfg(feature = "tree-sitter")]
+    (Command::NextNode, &["Alt+Right"]),
+    #[cfg(not(feature = "tree-sitter"))]
+    (Command::SelectNode, &[]),
+    #[cfg(not(feature = "tree-sitter"))]
+    (Command::ParentNode, &[]),
+    #[cfg(not(feature = "tree-sitter"))]
+    (Command::NextNode, &[]),
+];
+
+const HEX: &[(Command, &[&str])] = &[
+    (Command::Open, &["Ctrl+O"]),
+    (Command::Save, &["Ctrl+S"]),
+    (Command::Quit, &["Ctrl+Q"]),
+    (Command::Close, &["Ctrl+W"]),
+    (Command::NextBuffer, &["Alt+N", "Ctrl+PageDown"]),
+    (Command::PreviousBuffer, &["Alt+P", "Ctrl+PageUp"]),
+    (Command::BufferList, &["Alt+B"]),
+    (Command::SplitHorizontal, &["Alt+H"]),
+    (Command::SplitVertical, &["Alt+V"]),
+    (Command::ClosePane, &["Alt+X"]),
+    (Command::NextPane, &["Alt+O"]),
+    (Command::Reload, &["Alt+R"]),
+    (Command::MoveUp, &["Up"]),
+    (Command::MoveDown, &["Down"]),
+    (Command::MoveLeft, &["Left"]),
+    (Command::MoveRight, &["Right"]),
+    (Command::PageUp, &["PageUp"]),
+    (Command::PageDown, &["PageDown"]),
+    (Command::LineStart, &["Home"]),
+    (Command::LineEnd, &["End"]),
+];
+
+const OPEN: &[(Command, &[&str])] = &[
+    (Command::Select, &["Enter"]),
+    (Command::Cancel, &["Ctrl+C"]),
+    (Command::MoveUp, &["Up"]),
+    (Command::MoveDown, &["Down"]),
+];
+
+const SAVE_AS: &[(Command, &[&str])] = &[
+    (Command::Select, &["Enter"]),
+    (Command::Cancel, &["Ctrl+C"]),
+    (Command::ToggleInput, &["Tab"]),
+    (Command::FocusList, &["Esc"]),
+    (Command::MoveUp, &["Up"]),
+    (Command::MoveDown, &["Down"]),
+    (Command::MoveLeft, &["Left"]),
+    (Command::MoveRight, &["Right"]),
+    (Command::DeleteBackward, &["Backspace"]),
+];
+
+//検索と置換で共通
+const SEARCH: &[(Command, &[&str])] = &[
+    (Command::Select, &["Enter"]),
+    (Command::Cancel, &["Ctrl+C", "Esc"]),
+    (Command::NextMatch, &["Down", "Ctrl+N"]),
+    (Command::PreviousMatch, &["Up", "Ctrl+P"]),
+    (Command::ToggleRegex, &["Alt+R"]),
+    (Command::ToggleIgnoreCase, &["Alt+C"]),
+    (Command::ToggleWord, &["Alt+W"]),
+    (Command::MoveLeft, &["Left"]),
+    (Command::MoveRight, &["Right"]),
+    (Command::DeleteBackward, &["Backspace"]),
+];
+
+//置換するか1つずつ聞いているとき
+const REPLACE: &[(Command, &[&str])] = &[
+    (Command::ReplaceMatch, &["y"]),
+    (Command::SkipMatch, &["n"]),
+    (Command::ReplaceAll, &["a"]),
+    (Command::Cancel, &["q", "Ctrl+C", "Esc"]),
+];
+
+const LIST: &[(Command, &[&str])] = &[
+    (Command::Select, &["Enter"]),
+    (Command::Cancel, &["Ctrl+C", "Esc"]),
+    (Command::MoveUp, &["Up"]),
+    (Command::MoveDown, &["Down"]),
+];
+
+const REOPEN: &[(Command, &[&str])] = &[
+    (Command::Select, &["Enter"]),
+    (Command::Cancel, &["Ctrl+C", "Esc"]),
+    (Command::MoveLeft, &["Left", "BackTab"]),
+    (Command::MoveRight, &["Right", "Tab"]),
+];
+
+//変更済みのファイルを閉じる前に、保存するか破棄するかを聞くとき
+const CONFIRM: &[(Command, &[&str])] = &[
+    (Command::Save, &["y"]),
+    (Command::Discard, &["n"]),
+    (Command::Cancel, &["c", "Ctrl+C", "Esc"]),
+];
+
+//1つのモードのキーの割り当て。キーは"Ctrl+K Ctrl+S"のように続けて押す組み合わせでもよい
+#[derive(Clone)]
+pub struct Keymap {
+    commands: &'static [(Command, &'static [&'static str])],
+    bindings: Vec<(Vec<KeyEvent>, Command)>,
+}
+
+impl Keymap {
+    fn new(commands: &'static [(Command, &'static [&'static str])]) -> Self {
+        let bindings = Vec::new();
+        let mut keymap = Self { commands, bindings };
+        for &(command, specs) in commands {
+            for spec in specs {
+                keymap.bindings.push((parse_chord(spec).unwrap(), command));
+            }
+        }
+        return keymap;
+    }
+    //chordにcommandを割り当てる。noneなら割り当てを外す
+    //途中まで同じ組み合わせがあると続きを待つか決められないので、前の割り当ては外す
+    pub fn bind(&mut self, spec: &str, name: &str) -> Result<(), String> {
+        let chord = parse_chord(spec)?;
+        let command = match name {
+            "none" => None,
+            _ => match self.commands.iter().find(|(c, _)| c.name() == name) {
+                Some(&(command, _)) => Some(command),
+                None => return Err(format!("{}というコマンドはありません", name)),
+            },
+        };
+        self.bindings
+            .retain(|(keys, _)| !keys.starts_with(&chord) && !chord.starts_with(keys));
+        if let Some(command) = command {
+            self.bindings.push((chord, command));
+        }
+        return Ok(());
+    }
+}
+
+//設定できるモードごとのキーの割り当て
+#[derive(Clone)]
+pub struct Keymaps {
+    pub normal: Keymap,
+    pub hex: Keymap,
+    pub open: Keymap,
+    pub save_as: Keymap,
+    pub search: Keymap,
+    pub replace: Keymap,
+    pub list: Keymap,
+    pub reopen: Keymap,
+    pub confirm: Keymap,
+}
+
+impl Keymaps {
+    pub fn new() -> Self {
+        let normal = Keymap::new(NORMAL);
+        let hex = Keymap::new(HEX);
+        let open = Keymap::new(OPEN);
+        let save_as = Keymap::new(SAVE_AS);
+        let search = Keymap::new(SEARCH);
+        let replace = Keymap::new(REPLACE);
+        let list = Keymap::new(LIST);
+        let reopen = Keymap::new(REOPEN);
+        let confirm = Keymap::new(CONFIRM);
+        return Self {
+            normal,
+            hex,
+            open,
+            save_as,
+            search,
+            replace,
+            list,
+            reopen,
+            confirm,
+        };
+    }
+    //設定ファイルの[keys.normal]などの名前で探す
+    pub fn get_mut(&mut self, mode: &str) -> Option<&mut Keymap> {
+        return match mode {
+            "normal" => Some(&mut self.normal),
+            "hex" => Some(&mut self.hex),
+            "open" => Some(&mut self.open),
+            "save_as" => Some(&mut self.save_as),
+            "search" => Some(&mut self.search),
+            "replace" => Some(&mut self.replace),
+            "list" => Some(&mut self.list),
+            "reopen" => Some(&mut self.reopen),
+            "confirm" => Some(&mut self.confirm),
+            _ => None,
+        };
+    }
+}
+
+//押したキーをコマンドにした結果
+pub enum Input {
+    Command(Command),
+    //組み合わせの途中なので次のキーを待つ
+    Pending,
+    //組み合わせの続きが割り当てられていなかった
+    Unbound(String),
+    //どのコマンドでもない1つのキー。文字の入力などはモードが扱う
+    Key(KeyEvent),
+}
+
+//組み合わせの途中まで押したキー
+pub struct Chord {
+    keys: Vec<KeyEvent>,
+}
+
+impl Chord {
+    pub fn new() -> Self {
+        let keys = Vec::new();
+        return Self { keys };
+    }
+    pub fn input(&mut self, keymap: &Keymap, key: KeyEvent) -> Input {
+        self.keys.push(normalize(key));
+        if let Some((_, command)) = keymap.bindings.iter().find(|(keys, _)| *keys == self.keys) {
+            self.keys.clear();
+            return Input::Command(*command);
+        }
+        if keymap
+            .bindings
+            .iter()
+            .any(|(keys, _)| keys.starts_with(&self.keys))
+        {
+            return Input::Pending;
+        }
+        let keys = take(&mut self.keys);
+        if keys.len() == 1 {
+            return Input::Key(key);
+        }
+        return Input::Unbound(name(&keys));
+    }
+    //ステータス行に表示する、続きを待っているキー
+    pub fn pending(&self) -> String {
+        if self.keys.is_empty() {
+            return String::new();
+        }
+        return format!("{} …", name(&self.keys));
+    }
+}
+
+//端末からイベントを1つ読む。deadlineまでに何も押されなければTimedOutのエラーを返す
+pub fn read(deadline: Option<Instant>) -> io::Result<Event> {
+    if let Some(deadline) = deadline {
+        if !poll(deadline.saturating_duration_since(Instant::now()))? {
+            return Err(io::Error::from(ErrorKind::TimedOut));
+        }
+    }
+    return read_event();
+}
+
+//文字のキーは大文字かどうかを文字で表すので、比べるときはShiftを外す。BackTabもShiftが付いて届く
+fn normalize(key: KeyEvent) -> KeyEvent {
+    let mut modifiers = key.modifiers;
+    if let KeyCode::Char(_) | KeyCode::BackTab = key.code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    return KeyEvent::new(key.code, modifiers);
+}
+
+//空白で区切った、続けて押すキーの組み合わせを読む
+fn parse_chord(spec: &str) -> Result<Vec<KeyEvent>, String> {
+    let chord = spec
+        .split_whitespace()
+        .map(parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if chord.is_empty() {
+        return Err(String::from("キーが空です"));
+    }
+    return Ok(chord);
+}
+
+//"Ctrl+Shift+Up"のように+でつないだキーの名前を読む。最後がキーで、その前が修飾キー
+fn parse(spec: &str) -> Result<KeyEvent, String> {
+    let invalid = || format!("キー{}が読めません", spec);
+    let mut parts = spec.split('+').collect::<Vec<_>>();
+    //"Ctrl++"のように+そのものを指すときは最後が空になる
+    if spec.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let name = parts.pop().ok_or_else(invalid)?;
+    let mut modifiers = KeyModifiers::NONE;
+    for part in parts {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(invalid()),
+        };
+    }
+    let code = match name.to_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        lower => match (lower.strip_prefix('f'), name.chars().count()) {
+            (Some(n), _) if !n.is_empty() => {
+                let n = n.parse::<u8>().map_err(|_| invalid())?;
+                KeyCode::F(n)
+            }
+            (_, 1) => KeyCode::Char(name.chars().next().unwrap()),
+            _ => return Err(invalid()),
+        },
+    };
+    //端末はCtrlやAltと一緒の文字を小文字で、Shiftと一緒の文字を大文字で送ってくる
+    //Shift+TabはBackTabとして届く
+    let code = match code {
+        KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        KeyCode::Char(c) if !modifiers.is_empty() => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    };
+    return Ok(normalize(KeyEvent::new(code, modifiers)));
+}
+
+//parseで読める形のキーの名前。組み合わせは空白で区切る
+fn name(keys: &[KeyEvent]) -> String {
+    return keys.iter().map(key_name).collect::<Vec<_>>().join(" ");
+}
+
+fn key_name(key: &KeyEvent) -> String {
+    let mut name = String::new();
+    let modifiers = [
+        (KeyModifiers::CONTROL, "Ctrl+"),
+        (KeyModifiers::ALT, "Alt+"),
+        (KeyModifiers::SHIFT, "Shift+"),
+    ];
+    for (modifier, prefix) in modifiers {
+        if key.modifiers.contains(modifier) {
+            name.push_str(prefix);
+        }
+    }
+    let code = match key.code {
+        KeyCode::Char(' ') => String::from("Space"),
+        //修飾キーと一緒の大文字はShiftも押している
+        KeyCode::Char(c) if c.is_ascii_uppercase() && !key.modifiers.is_empty() => {
+            format!("Shift+{}", c)
+        }
+        KeyCode::Char(c) if !key.modifiers.is_empty() => c.to_ascii_uppercase().to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => format!("{:?}", code),
+    };
+    name.push_str(&code);
+    return name;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        return KeyEvent::new(code, modifiers);
+    }
+
+    #[test]
+    fn parse_keys() {
+        let ctrl = KeyModifiers::CONTROL;
+        assert_eq!(
+            parse_chord("Ctrl+K  Ctrl+S"),
+            Ok(vec![
+                key(KeyCode::Char('k'), ctrl),
+                key(KeyCode::Char('s'), ctrl)
+            ])
+        );
+        assert_eq!(
+            parse("Shift+Tab"),
+            Ok(key(KeyCode::BackTab, KeyModifiers::NONE))
+        );
+        assert_eq!(parse("ctrl+shift+a"), Ok(key(KeyCode::Char('A'), ctrl)));
+        assert_eq!(parse("Alt+F5"), Ok(key(KeyCode::F(5), KeyModifiers::ALT)));
+        assert_eq!(parse("Ctrl++"), Ok(key(KeyCode::Char('+'), ctrl)));
+        assert!(parse_chord(" ").is_err());
+        assert!(parse("Hyper+A").is_err());
+        assert!(parse("Ctrl+Foo").is_err());
+    }
+
+    #[test]
+    fn chord() {
+        let mut keymap = Keymap::new(NORMAL);
+        keymap.bind("Ctrl+K Ctrl+S", "save").unwrap();
+        let ctrl = KeyModifiers::CONTROL;
+        let mut chord = Chord::new();
+        let input = chord.input(&keymap, key(KeyCode::Char('k'), ctrl));
+        assert!(matches!(input, Input::Pending));
+        assert_eq!(chord.pending(), "Ctrl+K …");
+        let input = chord.input(&keymap, key(KeyCode::Char('s'), ctrl));
+        assert!(matches!(input, Input::Command(Command::Save)));
+        assert_eq!(chord.pending(), "");
+        chord.input(&keymap, key(KeyCode::Char('k'), ctrl));
+        let input = chord.input(&keymap, key(KeyCode::Char('z'), KeyModifiers::NONE));
+        assert!(matches!(input, Input::Unbound(name) if name == "Ctrl+K z"));
+        //組み合わせでない1つのキーはそのまま返す。端末はShiftと一緒の文字を大文字で送る
+        let input = chord.input(&keymap, key(KeyCode::Char('A'), KeyModifiers::SHIFT));
+        assert!(matches!(input, Input::Key(k) if k.code == KeyCode::Char('A')));
+        let input = chord.input(&keymap, key(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert!(matches!(input, Input::Command(Command::Unindent)));
+    }
+
+    #[test]
+    fn bind() {
+        let mut keymap = Keymap::new(NORMAL);
+        let ctrl = KeyModifiers::CONTROL;
+        let save = vec![key(KeyCode::Char('s'), ctrl)];
+        //途中まで同じ組み合わせは外す
+        keymap.bind("Ctrl+S Ctrl+S", "save").unwrap();
+        assert!(!keymap.bindings.iter().any(|(keys, _)| *keys == save));
+        keymap.bind("Ctrl+S", "quit").unwrap();
+        assert!(!keymap.bindings.iter().any(|(keys, _)| 1 < keys.len()));
+        assert!(keymap
+            .bindings
+            .iter()
+            .any(|(keys, command)| *keys == save && *command == Command::Quit));
+        keymap.bind("Ctrl+S", "none").unwrap();
+        assert!(!keymap.bindings.iter().any(|(keys, _)| *keys == save));
+        assert!(keymap.bind("Ctrl+S", "fly").is_err());
+        //モードにないコマンドは割り当てられない
+        assert!(Keymap::new(OPEN).bind("Ctrl+S", "save").is_err());
+    }
+}
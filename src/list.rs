@@ -0,0 +1,101 @@
+use crate::{
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    theme, Cursor, Mode,
+};
+use crossterm::{
+    cursor::{MoveTo, Show},
+    event::Event,
+    style::{Attribute, Color, SetAttribute, SetForegroundColor},
+    terminal::{window_size, Clear, ClearType},
+};
+use std::io::{Stdout, Write};
+
+//開いているファイルの一覧から切り替え先を選ぶ
+pub struct List {
+    cursor: Cursor,
+    chord: Chord,
+}
+
+impl List {
+    pub fn new() -> Self {
+        let cursor = Cursor::new();
+        let chord = Chord::new();
+        return Self { cursor, chord };
+    }
+    //一覧を開くときは今のファイルを選んだ状態にする
+    pub fn start(&mut self, current: usize) {
+        self.cursor.y = current;
+    }
+    //entriesは(パス, 変更済みか)。選んだ番号を返す
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        mode: &mut Mode,
+        entries: &[(String, bool)],
+    ) -> Option<usize> {
+        self.output(stdout, entries);
+        return self.input(stdout, mode, entries.len());
+    }
+    fn output(&mut self, stdout: &mut Stdout, entries: &[(String, bool)]) {
+        let size = window_size().unwrap();
+        let height = size.rows.saturating_sub(1) as usize;
+        write!(stdout, "{}", Clear(ClearType::All)).unwrap();
+        write!(
+            stdout,
+            "{}{}[バッファ一覧]{} {}",
+            MoveTo(0, 0),
+            SetForegroundColor(theme::current().header),
+            SetForegroundColor(Color::Reset),
+            self.chord.pending()
+        )
+        .unwrap();
+        let offset = (self.cursor.y + 1).saturating_sub(height);
+        for (i, (path, modified)) in entries.iter().enumerate().skip(offset).take(height) {
+            let path = if path.is_empty() { "無題" } else { path };
+            let modified = if *modified { " 変更済み" } else { "" };
+            let y = (i - offset) as u16 + 1;
+            write!(stdout, "{}", MoveTo(0, y)).unwrap();
+            if i == self.cursor.y {
+                write!(stdout, "{}", SetAttribute(Attribute::Underlined)).unwrap();
+            }
+            write!(
+                stdout,
+                "{} {}{}{}",
+                i + 1,
+                path,
+                modified,
+                SetAttribute(Attribute::NoUnderline)
+            )
+            .unwrap();
+        }
+        stdout.flush().unwrap();
+    }
+    fn input(&mut self, stdout: &mut Stdout, mode: &mut Mode, len: usize) -> Option<usize> {
+        let mut selected = None;
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let input = self.chord.input(&config::current().keymaps.list, key);
+            match input {
+                Input::Command(Command::Cancel) => self.back(stdout, mode),
+                Input::Command(Command::Select) => {
+                    selected = Some(self.cursor.y);
+                    self.back(stdout, mode);
+                }
+                Input::Command(Command::MoveUp) if 0 < self.cursor.y => {
+                    self.cursor.y -= 1;
+                }
+                Input::Command(Command::MoveDown) if self.cursor.y + 1 < len => {
+                    self.cursor.y += 1;
+                }
+                _ => {}
+            }
+        }
+        return selected;
+    }
+    //戻り先のモードはmainが今のファイルに合わせて決める
+    fn back(&mut self, stdout: &mut Stdout, mode: &mut Mode) {
+        *mode = Mode::Normal;
+        write!(stdout, "{}", Show).unwrap();
+    }
+}
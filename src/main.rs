@@ -1,99 +1,436 @@
+#![allow(clippy::needless_return)]
+mod buffer;
+mod buffers;
+mod clipboard;
+mod command;
+mod config;
+mod confirm;
 mod cursor;
 mod dir;
+mod error;
+mod file;
+mod hex;
+mod history;
+mod keys;
+mod list;
 mod mode;
 mod normal;
 mod open;
+mod reopen;
+mod screen;
+mod search;
+mod syntax;
+mod tabs;
+mod theme;
+#[cfg(feature = "tree-sitter")]
+mod tree;
+mod windows;
 mod write_path;
+use buffer::Buffer;
+use buffers::{Buffers, View};
+use clipboard::Clipboard;
+use confirm::{Answer, Confirm, Pending};
 use crossterm::{
-    cursor::{SetCursorStyle, Show},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::{Hide, SetCursorStyle, Show},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, window_size},
     terminal::{Clear, ClearType},
 };
 use cursor::Cursor;
 use dir::Dir;
-use mode::Mode;
+use error::Error;
+use file::{read, save, save_bytes};
+use list::List;
+use mode::{Action, Mode};
 use normal::Normal;
 use open::Open;
+use reopen::Reopen;
+use screen::{Screen, Style};
+use search::Search;
 use std::{
     env::args,
-    fs::File,
-    io::{stdout, BufReader, BufWriter, Read, Write},
+    io::{stdout, ErrorKind, Write},
+    panic,
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
+use tabs::Tabs;
+use windows::{Split, Windows};
 use write_path::WritePath;
 fn main() {
+    //パニックしても端末を元に戻してからメッセージを出す
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
     enable_raw_mode().unwrap();
-    let mut args = args();
+    //新しいバッファの文字コードなどに使うので、バッファを作る前に設定を読む
+    let loaded = load_config();
+    if loaded.is_err() {
+        theme::set(theme::DARK);
+    }
     let mut dir = Dir::new();
-    let mut buffer = Vec::<String>::new();
-    match args.nth(1) {
-        Some(s) => {
-            let file = File::open(&s).unwrap();
-            let mut reader = BufReader::new(file);
-            let mut b = String::new();
-            reader.read_to_string(&mut b).unwrap();
-            buffer = b.lines().map(|s| s.to_string()).collect::<Vec<_>>();
-            dir.path = s;
-        }
-        None => {
-            buffer.push(String::new());
+    //引数のファイルをそれぞれバッファとして開き、最初のファイルを表示する
+    let mut buffers = Buffers::new(View::Text(Box::new(Normal::new(Buffer::new()))));
+    for (i, s) in args().skip(1).enumerate() {
+        let (view, path) = open_arg(s);
+        if i == 0 {
+            *buffers.view() = view;
+            dir.path = path;
+        } else {
+            buffers.add(view, path, &mut dir);
         }
     }
+    buffers.switch(0, &mut dir);
+    if let Err(e) = loaded {
+        buffers.view().set_msg(e.to_string());
+    }
     let mut stdout = stdout();
-    let mut mode = Mode::Normal;
-    let mut normal = Normal::new(buffer, &mut stdout, &dir);
+    let mut mode = buffers.mode();
+    let mut screen = Screen::new();
     let mut write_path = WritePath::new();
     let mut open = Open::new();
+    let mut search = Search::new();
+    let mut reopen = Reopen::new();
+    let mut list = List::new();
+    let mut confirm = Confirm::new();
+    let mut windows = Windows::new();
+    let mut tabs = Tabs::new();
+    let mut pending = None;
+    //終了するときに破棄すると答えたファイル
+    let mut discarded = Vec::new();
+    let mut clipboard = Clipboard::new();
+    let mut autosaved = Instant::now();
     write!(stdout, "{}", SetCursorStyle::SteadyBar).unwrap();
+    execute!(stdout, EnableBracketedPaste, EnableMouseCapture).unwrap();
     stdout.flush().unwrap();
-    let mut flag: bool;
     loop {
         let mut path = String::new();
+        let mut action = Action::None;
+        //編集中の画面にいるときだけ、間隔が過ぎていれば自動で保存する
+        let interval = config::current().autosave;
+        if let Some(interval) = interval {
+            if matches!(mode, Mode::Normal | Mode::Hex) && interval <= autosaved.elapsed() {
+                autosave(&mut buffers, &mut dir);
+                autosaved = Instant::now();
+            }
+        }
+        let deadline = interval.map(|interval| autosaved + interval);
+        if matches!(
+            mode,
+            Mode::Normal | Mode::Hex | Mode::Search | Mode::Replace | Mode::Confirm | Mode::Reopen
+        ) {
+            layout(&mut screen, &mut windows, &mut tabs, &mut buffers, &dir);
+        }
         match mode {
             Mode::Normal => {
-                (path, flag) = normal.run(&mut stdout, &mut mode, &dir);
-                if flag {
-                    break;
+                if let Some(normal) = buffers.normal() {
+                    (path, action) = normal.run(
+                        &mut stdout,
+                        &mut screen,
+                        &mut mode,
+                        &dir,
+                        &mut clipboard,
+                        deadline,
+                    );
+                    if matches!(mode, Mode::Search | Mode::Replace) {
+                        search.start(normal, matches!(mode, Mode::Replace));
+                    }
+                    if matches!(mode, Mode::Reopen) {
+                        reopen.start(normal, &mut mode, &dir.path);
+                    }
                 }
-                if matches!(mode, Mode::WritePath) {
-                    write_path.buffer = normal.buffer.clone();
+                if matches!(mode, Mode::List) {
+                    list.start(buffers.current);
                 }
             }
             Mode::WritePath => {
                 path = write_path.run(&mut stdout, &mut mode, &mut dir);
+                screen.invalidate();
+            }
+            Mode::Search | Mode::Replace => {
+                if let Some(normal) = buffers.normal() {
+                    search.run(&mut stdout, &mut screen, &mut mode, normal, &dir);
+                }
+            }
+            Mode::Reopen => {
+                if let Some(normal) = buffers.normal() {
+                    reopen.run(&mut stdout, &mut screen, &mut mode, normal, &dir);
+                }
             }
             Mode::Open => {
                 let p = open.run(&mut stdout, &mut mode, &mut dir);
-                if let Ok(file) = File::open(&p) {
-                    let mut reader = BufReader::new(file);
-                    let mut b = String::new();
-                    if let Ok(_) = reader.read_to_string(&mut b) {
-                        buffer = b.lines().map(|s| s.to_string()).collect::<Vec<_>>();
-                        normal.set_buffer(buffer);
-                        dir.path = p;
+                screen.invalidate();
+                if !p.is_empty() {
+                    //開いているファイルならそれに切り替える
+                    match buffers.find(&p, &dir) {
+                        Some(i) => buffers.switch(i, &mut dir),
+                        None => match read(&p) {
+                            Ok(content) => buffers.add(View::new(content), p, &mut dir),
+                            Err(e) => buffers.view().set_msg(e.to_string()),
+                        },
+                    }
+                }
+                if matches!(mode, Mode::Normal) {
+                    mode = buffers.mode();
+                }
+            }
+            Mode::Hex => {
+                if let Some(hex) = buffers.hex() {
+                    (path, action) = hex.run(&mut stdout, &mut screen, &mut mode, &dir, deadline);
+                }
+                if matches!(mode, Mode::List) {
+                    list.start(buffers.current);
+                }
+            }
+            Mode::List => {
+                let entries = buffers.entries(&dir);
+                if let Some(i) = list.run(&mut stdout, &mut mode, &entries) {
+                    buffers.switch(i, &mut dir);
+                }
+                screen.invalidate();
+                if matches!(mode, Mode::Normal) {
+                    mode = buffers.mode();
+                }
+            }
+            Mode::Confirm => {
+                match confirm.run(&mut stdout, &mut screen, buffers.view(), &dir) {
+                    None => {}
+                    Some(Answer::Cancel) => {
+                        pending = None;
+                        discarded.clear();
+                        mode = buffers.mode();
+                    }
+                    //無題のファイルは保存先を決めてもらい、続きの操作はやめる
+                    Some(Answer::Save) if dir.path.is_empty() => {
+                        pending = None;
+                        discarded.clear();
+                        write!(stdout, "{}", Hide).unwrap();
+                        mode = Mode::WritePath;
+                    }
+                    Some(answer) => {
+                        mode = buffers.mode();
+                        let saved = match answer {
+                            Answer::Save => {
+                                save_view(buffers.view(), dir.path.clone(), &mut dir.path)
+                            }
+                            _ => {
+                                discarded.push(buffers.current);
+                                true
+                            }
+                        };
+                        match pending.take() {
+                            Some(Pending::Quit) if saved => action = Action::Quit,
+                            Some(Pending::Close) if saved => action = Action::Close,
+                            _ => discarded.clear(),
+                        }
                     }
                 }
             }
         }
-        if !path.is_empty() {
-            if path == dir.path {
-                normal.update();
+        match action {
+            Action::None => {}
+            //変更済みのファイルが残っていれば、1つずつ保存するか聞いてから終了する
+            Action::Quit => {
+                let modified =
+                    (0..buffers.len()).find(|&i| buffers.modified(i) && !discarded.contains(&i));
+                match modified {
+                    Some(i) => {
+                        buffers.switch(i, &mut dir);
+                        pending = Some(Pending::Quit);
+                        mode = Mode::Confirm;
+                    }
+                    None => break,
+                }
+            }
+            Action::Close => {
+                if buffers.modified(buffers.current) && !discarded.contains(&buffers.current) {
+                    pending = Some(Pending::Close);
+                    mode = Mode::Confirm;
+                } else {
+                    let i = buffers.current;
+                    buffers.close(&mut dir);
+                    windows.remove_buffer(i, buffers.current);
+                    discarded.clear();
+                    mode = buffers.mode();
+                }
+            }
+            Action::Next => {
+                buffers.next(&mut dir);
+                mode = buffers.mode();
             }
-            if dir.path.is_empty() {
-                dir.path = path.clone();
-                normal.update();
+            Action::Previous => {
+                buffers.previous(&mut dir);
+                mode = buffers.mode();
+            }
+            Action::Split(split) => {
+                let small = match split {
+                    Split::Horizontal => screen.height < 4,
+                    Split::Vertical => screen.width < 5,
+                };
+                if small {
+                    buffers
+                        .view()
+                        .set_msg(String::from("これ以上分割できません"));
+                } else {
+                    windows.split(split);
+                }
+            }
+            Action::ClosePane => {
+                if windows.close() {
+                    focus(&mut windows, &mut buffers, &mut dir);
+                    mode = buffers.mode();
+                } else {
+                    buffers
+                        .view()
+                        .set_msg(String::from("最後の画面は閉じられません"));
+                }
+            }
+            Action::NextPane => {
+                windows.next();
+                focus(&mut windows, &mut buffers, &mut dir);
+                mode = buffers.mode();
+            }
+            Action::Tab(x) => {
+                if let Some(i) = tabs.find(x) {
+                    buffers.switch(i, &mut dir);
+                    mode = buffers.mode();
+                }
+            }
+            //読めなかったときは今の設定のまま理由を表示する
+            Action::Reload => {
+                let msg = match load_config() {
+                    Ok(()) => String::from("設定を読み直しました"),
+                    Err(e) => e.to_string(),
+                };
+                buffers.view().redraw();
+                buffers.view().set_msg(msg);
             }
-            save(&normal.buffer, path);
+        }
+        if !path.is_empty() {
+            save_view(buffers.view(), path, &mut dir.path);
         }
         sleep(Duration::from_millis(5));
     }
-    write!(stdout, "{}{}", Clear(ClearType::All), Show).unwrap();
-    disable_raw_mode().unwrap();
+    restore();
+}
+
+//端末の大きさから画面の分割を決め、一番上のタブ行と、今の画面以外の画面と境目を描く
+//screenの描く範囲は今の画面にしておく
+fn layout(
+    screen: &mut Screen,
+    windows: &mut Windows,
+    tabs: &mut Tabs,
+    buffers: &mut Buffers,
+    dir: &Dir,
+) {
+    let size = window_size().unwrap();
+    screen.resize(size.columns, size.rows);
+    tabs.output(screen, &buffers.entries(dir), buffers.current);
+    let rows = size.rows.saturating_sub(1);
+    let (rects, borders) = windows.layout((0, 1, size.columns, rows));
+    let pane = windows.pane();
+    pane.buffer = buffers.current;
+    pane.view = buffers.view().view();
+    for (i, pane) in windows.panes().into_iter().enumerate() {
+        if i != windows.focus {
+            let (x, y, width, height) = rects[i];
+            screen.set_area(x, y, width, height);
+            buffers.draw(pane.buffer, pane.view.clone(), screen, dir);
+        }
+    }
+    screen.set_area(0, 0, size.columns, size.rows);
+    let gray = Style::fg(theme::current().gutter);
+    for (x, y, _, height) in borders {
+        for y in y..y + height {
+            screen.put(x, y, "│", gray);
+        }
+    }
+    let (x, y, width, height) = rects[windows.focus];
+    screen.set_area(x, y, width, height);
+    let view = buffers.view();
+    view.resize(width, height);
+    //他の画面が同じバッファを描いていることがあるので、毎回全体を描き直す
+    if 1 < windows.len() {
+        view.redraw();
+    }
+}
+
+//今の画面のバッファとカーソルに切り替える
+fn focus(windows: &mut Windows, buffers: &mut Buffers, dir: &mut Dir) {
+    let pane = windows.pane();
+    buffers.switch(pane.buffer, dir);
+    buffers.view().set_view(pane.view.clone());
+}
+
+//設定ファイルと、設定で選んだテーマを読む。どちらかが読めなければ何も変えずにエラーを返す
+fn load_config() -> Result<(), Error> {
+    let config = config::load()?;
+    let theme = theme::load(&config.theme_path())?;
+    config::set(config);
+    theme::set(theme);
+    return Ok(());
 }
 
-fn save(buffer: &Vec<String>, path: String) {
-    let file = File::create(path).unwrap();
-    let mut writer = BufWriter::new(file);
-    write!(writer, "{}\n", buffer.join("\n")).unwrap();
+//名前のある変更済みのファイルをすべて保存する
+fn autosave(buffers: &mut Buffers, dir: &mut Dir) {
+    for i in 0..buffers.len() {
+        let (view, path) = buffers.get(i, dir);
+        if view.modified() && !path.is_empty() {
+            save_view(view, path.clone(), path);
+        }
+    }
+}
+
+//viewをpathに保存する。viewのファイルのパスcurrentに保存したとき、または無題だったときは保存済みにする
+fn save_view(view: &mut View, path: String, current: &mut String) -> bool {
+    let result = match view {
+        View::Text(normal) => save(
+            &normal.buffer,
+            &path,
+            normal.create_dir() && path == *current,
+        ),
+        View::Binary(hex) => save_bytes(&hex.bytes, &path),
+    };
+    if let Err(e) = result {
+        view.set_msg(e.to_string());
+        return false;
+    }
+    if current.is_empty() {
+        *current = path.clone();
+    }
+    if path == *current {
+        match view {
+            View::Text(normal) => normal.update(),
+            View::Binary(hex) => hex.update(),
+        }
+    }
+    return true;
+}
+
+//引数のファイルを開いた表示とそのパス。開けなかったときは無題のバッファにエラーを表示する
+fn open_arg(s: String) -> (View, String) {
+    let mut normal = Normal::new(Buffer::new());
+    match read(&s) {
+        Ok(content) => return (View::new(content), s),
+        //存在しないファイルはその名前の空のバッファとして開く
+        Err(Error::Open(_, e)) if e.kind() == ErrorKind::NotFound => {
+            normal.new_file = true;
+            return (View::Text(Box::new(normal)), s);
+        }
+        Err(e) => {
+            normal.msg = e.to_string();
+            return (View::Text(Box::new(normal)), String::new());
+        }
+    }
+}
+
+//端末を生モードから戻す。パニックの途中でも呼ばれるので失敗は無視する
+fn restore() {
+    let mut stdout = stdout();
+    let _ = write!(stdout, "{}{}", Clear(ClearType::All), Show);
+    let _ = execute!(stdout, DisableBracketedPaste, DisableMouseCapture);
+    let _ = disable_raw_mode();
 }
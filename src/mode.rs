@@ -1,5 +1,29 @@
+use crate::windows::Split;
+
 pub enum Mode {
     Normal,
     WritePath,
     Open,
+    Search,
+    Replace,
+    Hex,
+    List,
+    Confirm,
+    Reopen,
+}
+
+//モードの処理からmainに頼む操作
+pub enum Action {
+    None,
+    Quit,
+    Close,
+    Next,
+    Previous,
+    Split(Split),
+    ClosePane,
+    NextPane,
+    //タブ行のx列目をクリックした
+    Tab(u16),
+    //設定ファイルを読み直す
+    Reload,
 }
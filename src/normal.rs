@@ -1,357 +1,1082 @@
-use crate::{Cursor, Dir, Mode};
+use crate::{
+    buffer::{Buffer, LineEnding},
+    command::Command,
+    config,
+    file::{read_as, ENCODINGS},
+    history::{Change, History, Kind},
+    keys::{self, Chord, Input},
+    screen::{Screen, Style},
+    syntax::Highlighter,
+    theme,
+    windows::Split,
+    Action, Clipboard, Cursor, Dir, Mode,
+};
 use crossterm::{
-    cursor::{Hide, MoveTo},
-    event::{read, Event, KeyCode, KeyModifiers},
-    style::{Color, SetForegroundColor},
-    terminal::{window_size, Clear, ClearType},
+    cursor::Hide,
+    event::{Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    style::Color,
+};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use regex::Regex;
+use std::{
+    cmp::Ordering,
+    io::{Stdout, Write},
+    path::Path,
+    time::Instant,
 };
-use std::io::{Stdout, Write};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 pub struct Normal {
     line: String,
     max: usize,
     cursor: Cursor,
-    pub buffer: Vec<String>,
-    buffer0: Vec<String>,
+    pub buffer: Buffer,
+    saved: usize,
+    saved_format: (LineEnding, &'static Encoding),
     diff: bool,
     buffer_offset: usize,
+    //折り返さないときに左端より左に隠れている表示幅
+    x_offset: usize,
     width: u16,
     height: u16,
-    old_all: (u16, u16, Vec<String>, usize),
+    old_all: Option<State>,
+    history: History,
+    pub highlight: Option<Regex>,
+    pub msg: String,
+    pub new_file: bool,
+    pub index: (usize, usize),
+    anchor: Option<Cursor>,
+    dirty: Option<(usize, usize)>,
+    starts: Vec<u16>,
+    highlighter: Highlighter,
+    chord: Chord,
+    create: Create,
 }
+
+//保存先のディレクトリがないときに作るか。Askは作ってよいか聞いている途中
+#[derive(PartialEq)]
+enum Create {
+    No,
+    Ask,
+    Yes,
+}
+//output_allが必要かどうかを決める値
+//(width, height, buffer_offset, x_offset, 行数, highlight, 選択範囲, (折り返すか, 行番号を表示するか))
+type State = (
+    u16,
+    u16,
+    usize,
+    usize,
+    usize,
+    Option<String>,
+    Option<((usize, usize), (usize, usize))>,
+    (bool, bool),
+);
 impl Normal {
-    pub fn new(buffer: Vec<String>, stdout: &mut Stdout, dir: &Dir) -> Self {
+    pub fn new(buffer: Buffer) -> Self {
         let line = String::new();
         let max = 0;
         let cursor = Cursor::new();
         let diff = false;
         let buffer_offset = 0;
+        let x_offset = 0;
         let width = 0;
         let height = 0;
-        let old_all = (width, height, buffer.clone(), buffer_offset);
+        let history = History::new();
+        let saved = history.revision();
+        let saved_format = (buffer.line_ending, buffer.encoding);
+        let old_all = None;
+        let highlight = None;
+        let msg = String::new();
+        let new_file = false;
+        let index = (1, 1);
+        let anchor = None;
+        let dirty = None;
+        let starts = Vec::new();
+        let highlighter = Highlighter::new(buffer.len_lines());
+        let chord = Chord::new();
+        let create = Create::No;
         let mut normal = Self {
-            line,                    //bufferに依存
-            max,                     //bufferに依存
-            cursor,                  //output_cursorを実行
-            buffer0: buffer.clone(), //output_msgを実行
+            line,          //bufferに依存
+            max,           //bufferに依存
+            cursor,        //output_cursorを実行
+            saved,         //output_msgを実行
+            saved_format,  //output_msgを実行
             buffer, //変更された行に対してoutput_linesを実行し、行数が変わった場合はoutput_allを実行
-            diff,   //bufferとbuffer0に依存
+            diff,   //historyとsavedに依存
             buffer_offset, //output_allを実行
+            x_offset, //output_allを実行
             width,  //output_allを実行
             height, //output_allを実行
             old_all,
+            history,
+            highlight, //output_allを実行
+            msg,       //output_msgを実行
+            new_file,  //output_msgを実行
+            index,     //output_msgを実行
+            anchor,    //output_allを実行
+            dirty,     //output_linesを実行
+            starts,
+            highlighter, //bufferに依存
+            chord,
+            create,
         };
         normal.set_data();
-        normal.output_all(stdout, dir);
         return normal;
     }
-    pub fn set_buffer(&mut self, buffer: Vec<String>) {
+    pub fn set_buffer(&mut self, buffer: Buffer) {
         self.buffer = buffer;
         self.cursor = Cursor::new();
+        self.anchor = None;
+        self.buffer_offset = 0;
+        self.history = History::new();
+        self.dirty = Some((0, self.buffer.len_lines()));
+        self.highlighter.reset(self.buffer.len_lines());
+        self.new_file = false;
         self.update();
     }
     fn set_data(&mut self) {
-        self.diff = self.buffer.eq(&self.buffer0);
-        self.line = self.buffer[self.cursor.y].clone();
-        self.max = self.buffer.len() - 1;
-        let size = window_size().unwrap();
-        self.width = size.columns;
-        self.height = size.rows;
-    }
-    fn update_data(&mut self) {
-        self.old_all = (
+        self.diff = !self.modified();
+        self.line = self.buffer.line(self.cursor.y).to_string();
+        self.max = self.buffer.len_lines() - 1;
+    }
+    //表示する範囲の大きさが変わったら、折り返しが変わってもカーソルが画面内に残るようにする
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.scroll_to_cursor();
+        }
+    }
+    fn state(&self) -> State {
+        let config = config::current();
+        return (
             self.width,
             self.height,
-            self.buffer.clone(),
             self.buffer_offset,
+            self.x_offset,
+            self.buffer.len_lines(),
+            self.highlight
+                .as_ref()
+                .map(|regex| regex.as_str().to_string()),
+            self.selection(),
+            (config.wrap, config.line_numbers),
         );
     }
-    pub fn run(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &Dir) -> (String, bool) {
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        screen: &mut Screen,
+        mode: &mut Mode,
+        dir: &Dir,
+        clipboard: &mut Clipboard,
+        deadline: Option<Instant>,
+    ) -> (String, Action) {
         self.set_data();
-        if self.old_all
-            == (
-                self.width,
-                self.height,
-                self.buffer.clone(),
-                self.buffer_offset,
-            )
-        {
-            self.output_all(stdout, dir);
-        }
-        let v = self.input(stdout, mode, dir);
-        self.update_data();
-        return v;
-    }
-    fn output_lines(&self, stdout: &mut Stdout, dir: &Dir, line1: usize, line2: usize) {
-        let l = self.buffer.len();
-        let mut x = self.cursor.x;
-        let mut y = self.cursor.y;
-        let lines = l.to_string().len();
-        let width = self.width as usize - lines - 1;
-        write!(stdout, "{}", Clear(ClearType::All)).unwrap();
-        let mut ln = 0;
-        let mut i = if line1 < self.buffer_offset {
-            line1
+        self.output(screen, &dir.path);
+        screen.flush(stdout);
+        return self.input(stdout, mode, dir, clipboard, deadline);
+    }
+    //他のバッファを表示した後など、screenの内容が分からないときに全体を描き直させる
+    pub fn redraw(&mut self) {
+        self.old_all = None;
+    }
+    //前回からの変化に応じて必要な部分だけをscreenに描く
+    pub fn output(&mut self, screen: &mut Screen, path: &str) {
+        //保存して拡張子が変わったときは色分けを最初からやり直す
+        if self.highlighter.set_path(path, self.buffer.len_lines()) {
+            self.redraw();
+        }
+        //構文木で色が変わった行も描き直す
+        #[cfg(feature = "tree-sitter")]
+        if let Some((line1, line2)) = self.highlighter.parse(&self.buffer) {
+            self.dirty = match self.dirty {
+                Some((a, b)) => Some((a.min(line1), b.max(line2))),
+                None => Some((line1, line2)),
+            };
+        }
+        //設定を読み直して折り返しが変わったときも、カーソルが画面内に残るようにする
+        if self.old_all.is_none() {
+            self.scroll_to_cursor();
+        }
+        self.scroll_horizontally();
+        let state = self.state();
+        if self.old_all.as_ref() != Some(&state) {
+            self.output_all(screen);
+        } else if let Some((line1, line2)) = self.dirty {
+            self.output_lines(screen, line1, line2);
+        }
+        self.output_msg(screen, path);
+        self.output_cursor(screen);
+        self.old_all = Some(state);
+        self.dirty = None;
+    }
+    //行番号と空白を合わせた幅と本文の幅。行番号を表示しないときは本文が左端から始まる
+    fn text_width(&self) -> (usize, usize) {
+        let gutter = if config::current().line_numbers {
+            self.buffer.len_lines().to_string().len() + 1
         } else {
-            self.buffer_offset
+            0
         };
-        let end = if line2 < l { l } else { line2 };
-        while i < end {
-            let mut output;
-            let mut line = self.buffer[i].clone();
-            output = format!(
-                "{}{}{}{}{} ",
-                MoveTo(0, ln as u16),
-                " ".repeat(lines - (i + 1).to_string().len()),
-                SetForegroundColor(Color::Rgb {
-                    r: 127,
-                    g: 127,
-                    b: 127
-                }),
-                i + 1,
-                SetForegroundColor(Color::Reset)
-            );
-            while width < line.width() {
-                let mut w = String::new();
-                for c in line.chars() {
-                    if w.width() + c.width().unwrap() > width {
-                        break;
-                    }
-                    w.push(c);
+        let width = (self.width as usize).saturating_sub(gutter).max(1);
+        return (gutter, width);
+    }
+    //折り返した行。折り返さない設定なら1行のまま
+    fn chunks<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        if !config::current().wrap {
+            return vec![line];
+        }
+        let (_, width) = self.text_width();
+        return wrap(line, width);
+    }
+    //折り返さないときは、カーソルが本文の幅に入るようにx_offsetを調整する
+    fn scroll_horizontally(&mut self) {
+        if config::current().wrap {
+            self.x_offset = 0;
+            return;
+        }
+        let (_, width) = self.text_width();
+        if self.cursor.x < self.x_offset {
+            self.x_offset = self.cursor.x;
+        }
+        if self.x_offset + width <= self.cursor.x {
+            self.x_offset = self.cursor.x + 1 - width;
+        }
+    }
+    //line1からline2までの行を描く。折り返しで行の位置が変わった場合は、位置が元に戻るまで続けて描く
+    fn output_lines(&mut self, screen: &mut Screen, line1: usize, line2: usize) {
+        let l = self.buffer.len_lines();
+        let height = self.height.saturating_sub(1);
+        let i = line1.max(self.buffer_offset);
+        let mut ln = match self.starts.get(i - self.buffer_offset) {
+            Some(&ln) => ln,
+            None => return,
+        };
+        let first = i;
+        let old = self.starts.split_off(i - self.buffer_offset);
+        let mut i = i;
+        while i < l && ln < height {
+            let k = i - first;
+            //行頭の色分けの状態が変わった行は、複数行のコメントなどが変わったので描き直す
+            if line2 <= i && old.get(k) == Some(&ln) && !self.highlighter.changed(&self.buffer, i) {
+                self.starts.extend_from_slice(&old[k..]);
+                return;
+            }
+            self.starts.push(ln);
+            ln = self.output_line(screen, i, ln, height);
+            i += 1;
+        }
+        for y in ln..height {
+            screen.clear_line(0, y);
+        }
+    }
+    //行iを画面のln行目から折り返して描き、次の行を描く位置を返す
+    fn output_line(&mut self, screen: &mut Screen, i: usize, ln: u16, height: u16) -> u16 {
+        let (gutter, _) = self.text_width();
+        let theme = theme::current();
+        let line = self.buffer.line(i);
+        let ranges = self.ranges(i);
+        let spans = self.highlighter.spans(&self.buffer, i);
+        let mut ln = ln;
+        let mut offset = 0;
+        for (k, w) in self.chunks(&line).iter().enumerate() {
+            if height <= ln {
+                break;
+            }
+            screen.clear_line(0, ln);
+            if k == 0 && 0 < gutter {
+                let number = format!("{:>1$}", i + 1, gutter - 1);
+                screen.put(0, ln, &number, Style::fg(theme.gutter));
+            }
+            let mut x = gutter as u16;
+            let mut column = 0;
+            for (j, c) in w.char_indices() {
+                //横にスクロールして隠れた文字は描かない。左端で半分だけ隠れる全角文字は空白にする
+                let cw = char_width(c);
+                column += cw;
+                if column <= self.x_offset {
+                    continue;
                 }
-                output = format!("{}{}{}", output, MoveTo(lines as u16 + 1, ln as u16), w);
-                line = line[w.len()..].to_string();
-                if w.width() < x {
-                    x -= w.width();
-                    y += 1;
+                if column - cw < self.x_offset {
+                    x = screen.put(x, ln, &" ".repeat(column - self.x_offset), Style::new());
+                    continue;
                 }
-                if i < self.cursor.y {
-                    y += 1;
+                if screen.width <= x {
+                    break;
                 }
-                ln += 1;
+                //重なったときは後ろの範囲を優先する
+                let mut style = match spans
+                    .iter()
+                    .rev()
+                    .find(|&&(a, b, _)| a <= offset + j && offset + j < b)
+                {
+                    Some(&(_, _, scope)) => Style::fg(theme.scope(scope)),
+                    None => Style::new(),
+                };
+                let selected = ranges
+                    .iter()
+                    .any(|&(a, b)| a <= offset + j && offset + j < b);
+                if selected && theme.selection == Color::Reset {
+                    style.reverse = true;
+                } else if selected {
+                    style.bg = theme.selection;
+                }
+                x = screen.put(x, ln, &display(c), style);
             }
-            output = format!("{}{}{}", output, MoveTo(lines as u16 + 1, ln as u16), line);
+            offset += w.len();
             ln += 1;
-            i += 1;
-            if ln + 1 > self.height {
-                break;
+        }
+        return ln;
+    }
+    //行iの中で反転表示する範囲(バイト位置)。highlightに一致する部分と選択範囲
+    fn ranges(&self, i: usize) -> Vec<(usize, usize)> {
+        let mut ranges = match &self.highlight {
+            Some(regex) => regex
+                .find_iter(&self.buffer.line(i))
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(((sy, sx), (ey, ex))) = self.selection() {
+            if sy <= i && i <= ey {
+                let start = if i == sy { sx } else { 0 };
+                let end = if i == ey {
+                    ex
+                } else {
+                    self.buffer.line(i).len()
+                };
+                ranges.push((start, end));
             }
-            write!(stdout, "{}", output).unwrap();
         }
-        self.output_msg(stdout, dir);
-        self.output_cursor(
-            stdout,
-            (x + 1 + lines) as u16,
-            (y - self.buffer_offset) as u16,
-        );
-        stdout.flush().unwrap();
+        return ranges;
     }
-    fn output_msg(&self, stdout: &mut Stdout, dir: &Dir) {
-        let path = if dir.path.is_empty() {
-            "無題"
+    fn output_msg(&self, screen: &mut Screen, path: &str) {
+        let path = if path.is_empty() { "無題" } else { path };
+        let new_file = if self.new_file {
+            "[新規ファイル]"
         } else {
-            &dir.path
+            ""
         };
         let msg = if self.diff { "" } else { "変更済み" };
-        write!(stdout, "{}[{}]{}", MoveTo(0, self.height - 1), path, msg).unwrap();
+        let y = self.height.saturating_sub(1);
+        screen.clear_line(0, y);
+        screen.put(
+            0,
+            y,
+            &format!(
+                "[{}/{}][{}]{}{} {}",
+                self.index.0, self.index.1, path, new_file, msg, self.msg
+            ),
+            Style::new(),
+        );
+        let mut format = format!(
+            "{} {}",
+            self.buffer.encoding.name(),
+            self.buffer.line_ending.name()
+        );
+        if self.buffer.bom {
+            format.push_str(" BOM");
+        }
+        if !self.buffer.final_newline {
+            format.push_str(" 末尾改行なし");
+        }
+        let x = self.width.saturating_sub(format.width() as u16);
+        screen.put(x, y, &format, Style::new());
     }
-    fn output_all(&self, stdout: &mut Stdout, dir: &Dir) {
-        self.output_lines(stdout, dir, self.buffer_offset, self.buffer.len());
+    fn output_all(&mut self, screen: &mut Screen) {
+        self.starts = vec![0];
+        self.output_lines(screen, self.buffer_offset, self.buffer.len_lines());
     }
-    fn output_cursor(&self, stdout: &mut Stdout, x: u16, y: u16) {
-        write!(stdout, "{}", MoveTo(x, y)).unwrap();
+    fn output_cursor(&self, screen: &mut Screen) {
+        let (gutter, _) = self.text_width();
+        let mut y = (self.buffer_offset..self.cursor.y)
+            .map(|i| self.rows(i))
+            .sum::<usize>();
+        let mut x = self.cursor.x - self.x_offset;
+        let chunks = self.chunks(&self.line);
+        for w in &chunks[..chunks.len() - 1] {
+            if x < str_width(w) {
+                break;
+            }
+            x -= str_width(w);
+            y += 1;
+        }
+        screen.set_cursor((x + gutter) as u16, y as u16);
     }
-    fn input(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &Dir) -> (String, bool) {
-        let mut flag = false;
+    fn input(
+        &mut self,
+        stdout: &mut Stdout,
+        mode: &mut Mode,
+        dir: &Dir,
+        clipboard: &mut Clipboard,
+        deadline: Option<Instant>,
+    ) -> (String, Action) {
+        let mut action = Action::None;
         let mut path = String::new();
-        if let Ok(Event::Key(key)) = read() {
-            match key.code {
-                KeyCode::Backspace => {
-                    self.delete();
+        let event = keys::read(deadline);
+        if let Ok(Event::Paste(text)) = &event {
+            self.msg.clear();
+            self.history.seal();
+            //端末からは改行が\rで届く
+            let text = text.replace("\r\n", "\n").replace('\r', "\n");
+            self.insert(&text, true);
+        }
+        if let Ok(Event::Mouse(mouse)) = event {
+            action = click(mouse);
+        }
+        if let Ok(Event::Key(key)) = event {
+            self.msg.clear();
+            //ディレクトリを作るか聞いているときは、y以外なら保存をやめる
+            if self.create == Create::Ask {
+                if key.code == KeyCode::Char('y') {
+                    self.create = Create::Yes;
+                    path = dir.path.clone();
+                } else {
+                    self.create = Create::No;
+                    self.msg = String::from("保存をやめました");
+                }
+                return (path, action);
+            }
+            let input = self.chord.input(&config::current().keymaps.normal, key);
+            match input {
+                Input::Command(command) => {
+                    (path, action) = self.execute(command, stdout, mode, dir, clipboard);
                 }
-                KeyCode::Char(c) => {
-                    if key.modifiers == KeyModifiers::CONTROL {
-                        match c {
-                            'o' => {
-                                write!(stdout, "{}", Hide).unwrap();
-                                *mode = Mode::Open;
-                            }
-                            's' => {
-                                if dir.path.is_empty() {
-                                    self.save_as(mode, stdout);
-                                } else {
-                                    path = dir.path.clone();
-                                    self.update();
-                                }
-                            }
-                            'a' => {
-                                self.save_as(mode, stdout);
-                            }
-                            'q' => {
-                                flag = true;
-                            }
-                            _ => {}
+                Input::Pending => {
+                    self.msg = self.chord.pending();
+                }
+                Input::Unbound(keys) => {
+                    self.msg = format!("{}は割り当てられていません", keys);
+                }
+                //CtrlやAltと一緒に押した文字は入力しない
+                Input::Key(key) => {
+                    if let KeyCode::Char(c) = key.code {
+                        if key.modifiers != KeyModifiers::CONTROL
+                            && key.modifiers != KeyModifiers::ALT
+                        {
+                            self.insert(&c.to_string(), false);
                         }
-                    } else {
-                        self.typing(c);
                     }
                 }
-                KeyCode::Up => {
-                    self.up();
+            }
+        }
+        return (path, action);
+    }
+    //キーに割り当てたコマンドを実行する
+    fn execute(
+        &mut self,
+        command: Command,
+        stdout: &mut Stdout,
+        mode: &mut Mode,
+        dir: &Dir,
+        clipboard: &mut Clipboard,
+    ) -> (String, Action) {
+        let mut action = Action::None;
+        let mut path = String::new();
+        match command {
+            Command::Open => {
+                write!(stdout, "{}", Hide).unwrap();
+                *mode = Mode::Open;
+            }
+            Command::Save => {
+                let parent = Path::new(&dir.path).parent().unwrap_or(Path::new(""));
+                if dir.path.is_empty() {
+                    self.save_as(mode, stdout);
+                } else if self.create == Create::No
+                    && !parent.as_os_str().is_empty()
+                    && !parent.exists()
+                {
+                    self.create = Create::Ask;
+                    self.msg = format!(
+                        "{}がありません。作って保存しますか？(y/n)",
+                        parent.display()
+                    );
+                } else {
+                    path = dir.path.clone();
                 }
-                KeyCode::Down => {
-                    self.down();
+            }
+            Command::SaveAs => {
+                self.save_as(mode, stdout);
+            }
+            Command::Quit => {
+                action = Action::Quit;
+            }
+            Command::Close => {
+                action = Action::Close;
+            }
+            Command::Copy => {
+                if let Some(text) = self.selected_text() {
+                    if !clipboard.copy(stdout, text) {
+                        self.msg = String::from("長すぎるので端末のクリップボードには送りません");
+                    }
                 }
-                KeyCode::Left => {
-                    self.left();
+            }
+            Command::Cut => {
+                if let Some(text) = self.selected_text() {
+                    if !clipboard.copy(stdout, text) {
+                        self.msg = String::from("長すぎるので端末のクリップボードには送りません");
+                    }
+                    self.delete_selection();
                 }
-                KeyCode::Right => {
-                    self.right();
+            }
+            Command::Paste => {
+                self.insert(clipboard.paste(), true);
+            }
+            Command::Undo => {
+                self.anchor = None;
+                self.undo();
+            }
+            Command::Redo => {
+                self.anchor = None;
+                self.redo();
+            }
+            Command::Find => {
+                self.history.seal();
+                *mode = Mode::Search;
+            }
+            Command::Replace => {
+                self.history.seal();
+                *mode = Mode::Replace;
+            }
+            Command::ToggleLineEnding => {
+                self.toggle_line_ending();
+            }
+            Command::Reopen => {
+                self.choose_encoding(mode, dir);
+            }
+            Command::NextEncoding => {
+                self.next_encoding();
+            }
+            Command::NextBuffer => {
+                action = Action::Next;
+            }
+            Command::PreviousBuffer => {
+                action = Action::Previous;
+            }
+            Command::BufferList => {
+                write!(stdout, "{}", Hide).unwrap();
+                *mode = Mode::List;
+            }
+            Command::SplitHorizontal => {
+                action = Action::Split(Split::Horizontal);
+            }
+            Command::SplitVertical => {
+                action = Action::Split(Split::Vertical);
+            }
+            Command::ClosePane => {
+                action = Action::ClosePane;
+            }
+            Command::NextPane => {
+                action = Action::NextPane;
+            }
+            Command::Reload => {
+                action = Action::Reload;
+            }
+            Command::MoveUp | Command::SelectUp => {
+                self.mark(command == Command::SelectUp);
+                self.up();
+            }
+            Command::MoveDown | Command::SelectDown => {
+                self.mark(command == Command::SelectDown);
+                self.down();
+            }
+            Command::MoveLeft | Command::SelectLeft => {
+                self.mark(command == Command::SelectLeft);
+                self.left();
+            }
+            Command::MoveRight | Command::SelectRight => {
+                self.mark(command == Command::SelectRight);
+                self.right();
+            }
+            Command::DeleteBackward => {
+                if self.selection().is_some() {
+                    self.delete_selection();
+                } else {
+                    self.delete();
                 }
-                KeyCode::Enter => {
-                    self.new_line();
+            }
+            Command::NewLine => {
+                self.insert("\n", false);
+            }
+            Command::Indent => {
+                if self.selection().is_some() {
+                    self.indent();
+                } else {
+                    self.insert(&" ".repeat(config::current().tab_width), false);
                 }
-                _ => {}
             }
+            Command::Unindent => {
+                self.unindent();
+            }
+            #[cfg(feature = "tree-sitter")]
+            Command::SelectNode => {
+                self.select_node();
+            }
+            #[cfg(feature = "tree-sitter")]
+            Command::ParentNode => {
+                self.jump_node(true);
+            }
+            #[cfg(feature = "tree-sitter")]
+            Command::NextNode => {
+                self.jump_node(false);
+            }
+            #[cfg(not(feature = "tree-sitter"))]
+            Command::SelectNode | Command::ParentNode | Command::NextNode => {
+                self.msg = String::from("構文木がありません");
+            }
+            _ => {}
         }
-        return (path, flag);
+        return (path, action);
     }
     pub fn update(&mut self) {
-        self.buffer0 = self.buffer.clone();
+        self.new_file = false;
+        self.history.seal();
+        self.saved = self.history.revision();
+        self.saved_format = (self.buffer.line_ending, self.buffer.encoding);
+    }
+    //保存するときに無いディレクトリを作ってよいと答えてもらったか
+    pub fn create_dir(&self) -> bool {
+        return self.create == Create::Yes;
+    }
+    pub fn modified(&self) -> bool {
+        return self.history.revision() != self.saved
+            || (self.buffer.line_ending, self.buffer.encoding) != self.saved_format;
+    }
+    //改行コードをLFとCRLFで切り替える。保存したときに反映される
+    //混在しているときは行末の\rを取り除いてLFにそろえる。取り除いた\rはundoで戻せる
+    fn toggle_line_ending(&mut self) {
+        if self.buffer.line_ending == LineEnding::Mixed {
+            self.begin();
+            self.history
+                .set_line_ending(&self.cursor, LineEnding::Mixed, LineEnding::Lf);
+            let last = self.buffer.len_lines() - 1;
+            for y in 0..=last {
+                let l = self.buffer.line(y).len();
+                if self.buffer.line(y).ends_with('\r') && (y < last || self.buffer.final_newline) {
+                    self.edit(y, l - 1, "\r", "", Kind::Edit);
+                }
+            }
+            self.end();
+            self.line = self.buffer.line(self.cursor.y).to_string();
+            self.cursor.x = self.cursor.x.min(str_width(&self.line));
+        }
+        self.buffer.line_ending = match self.buffer.line_ending {
+            LineEnding::Lf => LineEnding::Crlf,
+            LineEnding::Crlf | LineEnding::Mixed => LineEnding::Lf,
+        };
+        self.msg = format!("改行コードを{}にしました", self.buffer.line_ending.name());
+    }
+    //保存する文字コードを順に切り替える。UTF-16はBOMを付けて保存する
+    fn next_encoding(&mut self) {
+        let i = ENCODINGS.iter().position(|&e| e == self.buffer.encoding);
+        let encoding = ENCODINGS[i.map_or(0, |i| (i + 1) % ENCODINGS.len())];
+        self.buffer.encoding = encoding;
+        self.buffer.bom = encoding == UTF_16LE || encoding == UTF_16BE;
+        self.msg = format!("保存する文字コードを{}にしました", encoding.name());
+    }
+    //開き直す文字コードを選ばせる。選んだ後はreopenで読み直す
+    fn choose_encoding(&mut self, mode: &mut Mode, dir: &Dir) {
+        if dir.path.is_empty() || self.new_file {
+            self.msg = String::from("開き直すファイルがありません");
+            return;
+        }
+        if self.modified() {
+            self.msg = String::from("変更を保存してから開き直してください");
+            return;
+        }
+        *mode = Mode::Reopen;
+    }
+    pub fn reopen(&mut self, path: &str, encoding: &'static Encoding) {
+        match read_as(path, encoding) {
+            Ok(buffer) => {
+                self.set_buffer(buffer);
+                self.msg = format!("{}で開き直しました", encoding.name());
+            }
+            Err(e) => self.msg = e.to_string(),
+        }
     }
     fn save_as(&self, mode: &mut Mode, stdout: &mut Stdout) {
         write!(stdout, "{}", Hide).unwrap();
         *mode = Mode::WritePath;
     }
-    fn delete(&mut self) {
-        let mut w = String::new();
-        let mut s = String::new();
-        for c in self.line.chars() {
-            w.push(c);
-            if w.width() >= self.cursor.x {
-                s = String::from(c);
-                break;
+    //selectなら選択範囲を広げ、そうでなければ選択を解除する
+    fn mark(&mut self, select: bool) {
+        self.history.seal();
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor.clone());
+            }
+        } else {
+            self.anchor = None;
+        }
+    }
+    //選択範囲の始点と終点の(行, バイト位置)。何も選択されていなければNone
+    fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor.as_ref()?;
+        let a = (anchor.y, byte_index(&self.buffer.line(anchor.y), anchor.x));
+        let b = self.position();
+        return match a.cmp(&b) {
+            Ordering::Less => Some((a, b)),
+            Ordering::Greater => Some((b, a)),
+            Ordering::Equal => None,
+        };
+    }
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        return Some(self.buffer.text(start, end));
+    }
+    fn delete_selection(&mut self) {
+        if let Some(text) = self.selected_text() {
+            let ((sy, sx), _) = self.selection().unwrap();
+            self.edit(sy, sx, &text, "", Kind::Edit);
+            self.set_position(sy, sx);
+        }
+        self.anchor = None;
+    }
+    //選択範囲があれば置き換えて、カーソル位置にtextを入力する
+    //貼り付けた文字列は1文字でも続けて入力した文字とまとめずに、そのまま入れる
+    fn insert(&mut self, text: &str, paste: bool) {
+        if text.is_empty() {
+            return;
+        }
+        let group = self.selection().is_some();
+        if group {
+            self.history.begin(&self.cursor);
+        }
+        self.delete_selection();
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some('\n'), None) if !paste => self.new_line(),
+            (Some(c), None) if !paste => self.typing(c),
+            _ => {
+                let (y, x) = self.position();
+                self.edit(y, x, "", text, Kind::Edit);
+                let (y, x) = end_of(y, x, text);
+                self.set_position(y, x);
+            }
+        }
+        if group {
+            self.history.end();
+        }
+    }
+    //選択範囲にかかる行の(最初の行, 最後の行)
+    fn selected_lines(&self) -> Option<(usize, usize)> {
+        let ((sy, _), (ey, ex)) = self.selection()?;
+        if sy < ey && ex == 0 {
+            return Some((sy, ey - 1));
+        }
+        return Some((sy, ey));
+    }
+    fn indent(&mut self) {
+        let tab_width = config::current().tab_width;
+        if let Some((sy, ey)) = self.selected_lines() {
+            self.history.begin(&self.cursor);
+            for i in sy..=ey {
+                self.edit(i, 0, "", &" ".repeat(tab_width), Kind::Edit);
+            }
+            self.history.end();
+            self.shift(sy, ey, |x, _| x + tab_width);
+        }
+    }
+    fn unindent(&mut self) {
+        let (sy, ey) = match self.selected_lines() {
+            Some(lines) => lines,
+            None => (self.cursor.y, self.cursor.y),
+        };
+        self.history.begin(&self.cursor);
+        let mut removed = Vec::new();
+        for i in sy..=ey {
+            let n = self
+                .buffer
+                .line(i)
+                .chars()
+                .take(config::current().tab_width)
+                .take_while(|&c| c == ' ')
+                .count();
+            if 0 < n {
+                self.edit(i, 0, &" ".repeat(n), "", Kind::Edit);
             }
+            removed.push(n);
+        }
+        self.history.end();
+        self.shift(sy, ey, |x, i| x.saturating_sub(removed[i - sy]));
+    }
+    //行sy..=eyにあるカーソルと選択の始点の横位置をfで動かす
+    fn shift(&mut self, sy: usize, ey: usize, f: impl Fn(usize, usize) -> usize) {
+        if sy <= self.cursor.y && self.cursor.y <= ey {
+            self.cursor.x = f(self.cursor.x, self.cursor.y);
         }
-        let l = w.len();
+        if let Some(anchor) = &mut self.anchor {
+            if sy <= anchor.y && anchor.y <= ey {
+                anchor.x = f(anchor.x, anchor.y);
+            }
+        }
+        self.line = self.buffer.line(self.cursor.y).to_string();
+    }
+    fn delete(&mut self) {
         if 0 < self.cursor.x {
-            let line1 = &self.line[0..l - s.len()];
-            let line2 = &self.line[l..];
-            self.buffer[self.cursor.y] = format!("{}{}", line1, line2);
-            self.cursor.x -= s.width();
+            let l = byte_index(&self.line, self.cursor.x);
+            let s = self.line[..l].chars().last().unwrap();
+            self.edit(
+                self.cursor.y,
+                l - s.len_utf8(),
+                &s.to_string(),
+                "",
+                Kind::Delete,
+            );
+            self.cursor.x -= char_width(s);
         } else if 0 < self.cursor.y {
-            let mut buffer1 = self.buffer[0..self.cursor.y].to_vec();
-            let buffer2 = self.buffer[self.cursor.y].clone();
-            let buffer3 = self.buffer[self.cursor.y + 1..].to_vec();
-            let l = buffer1[self.cursor.y - 1].width();
-            buffer1[self.cursor.y - 1].push_str(&buffer2);
-            buffer1.extend(buffer3);
-            self.buffer = buffer1;
+            let l = self.buffer.line(self.cursor.y - 1).len();
+            let x = str_width(&self.buffer.line(self.cursor.y - 1));
+            self.edit(self.cursor.y - 1, l, "\n", "", Kind::Delete);
             self.move_up();
-            self.cursor.x = l;
+            self.cursor.x = x;
+            self.line = self.buffer.line(self.cursor.y).to_string();
         }
     }
     fn new_line(&mut self) {
-        if self.cursor.x == 0 {
-            let mut buffer1 = self.buffer[0..self.cursor.y].to_vec();
-            let buffer2 = self.buffer[self.cursor.y..].to_vec();
-            buffer1.push(String::new());
-            buffer1.extend(buffer2);
-            self.buffer = buffer1;
-            self.move_down();
-        } else {
-            let mut w = String::new();
-            for c in self.line.chars() {
-                w.push(c);
-                if w.width() >= self.cursor.x {
-                    break;
-                }
+        let l = byte_index(&self.line, self.cursor.x);
+        self.edit(self.cursor.y, l, "", "\n", Kind::Edit);
+        self.move_down();
+        self.cursor.x = 0;
+        self.line = self.buffer.line(self.cursor.y).to_string();
+    }
+    fn typing(&mut self, c: char) {
+        let l = byte_index(&self.line, self.cursor.x);
+        self.edit(self.cursor.y, l, "", &c.to_string(), Kind::Typing);
+        self.cursor.x += char_width(c);
+    }
+    //(y, x)からremovedを取り除いてinsertedを挿入する。改行を含んでもよい
+    fn apply(&mut self, y: usize, x: usize, removed: &str, inserted: &str) {
+        self.buffer.replace(y, x, removed, inserted);
+        self.highlighter.edit(
+            y,
+            removed.matches('\n').count(),
+            inserted.matches('\n').count(),
+        );
+        #[cfg(feature = "tree-sitter")]
+        self.highlighter
+            .edit_tree(&self.buffer, y, x, removed, inserted);
+        let end = y + inserted.matches('\n').count() + 1;
+        self.dirty = match self.dirty {
+            Some((line1, line2)) => Some((line1.min(y), line2.max(end))),
+            None => Some((y, end)),
+        };
+        self.max = self.buffer.len_lines() - 1;
+        self.line = self.buffer.line(self.cursor.y.min(self.max)).to_string();
+    }
+    fn edit(&mut self, y: usize, x: usize, removed: &str, inserted: &str, kind: Kind) {
+        self.apply(y, x, removed, inserted);
+        let change = Change {
+            y,
+            x,
+            removed: removed.to_string(),
+            inserted: inserted.to_string(),
+        };
+        self.history.push(change, &self.cursor, kind);
+    }
+    fn undo(&mut self) {
+        if let Some(step) = self.history.undo() {
+            for change in step.changes.iter().rev() {
+                self.apply(change.y, change.x, &change.inserted, &change.removed);
             }
-            let l = w.len();
-            let line1 = &self.line[0..l];
-            let line2 = &self.line[l..];
-            self.buffer[self.cursor.y] = String::from(line1);
-            let mut buffer1 = self.buffer[0..self.cursor.y + 1].to_vec();
-            let buffer2 = self.buffer[self.cursor.y + 1..].to_vec();
-            buffer1.push(String::from(line2));
-            buffer1.extend(buffer2);
-            self.buffer = buffer1;
-            self.move_down();
-            self.cursor.x = 0;
+            if let Some((before, _)) = step.line_ending {
+                self.buffer.line_ending = before;
+            }
+            self.cursor = step.cursor;
+            self.line = self.buffer.line(self.cursor.y).to_string();
+            self.scroll_to_cursor();
         }
     }
-    fn typing(&mut self, c: char) {
-        if 0 < self.cursor.x {
-            let mut w = String::new();
-            for c in self.line.chars() {
-                w.push(c);
-                if w.width() >= self.cursor.x {
-                    break;
-                }
+    fn redo(&mut self) {
+        if let Some(step) = self.history.redo() {
+            for change in step.changes.iter() {
+                self.apply(change.y, change.x, &change.removed, &change.inserted);
+            }
+            if let Some((_, after)) = step.line_ending {
+                self.buffer.line_ending = after;
             }
-            let l = w.len();
-            let line1 = &self.line[0..l];
-            let line2 = &self.line[l..];
-            self.buffer[self.cursor.y] = format!("{}{}{}", line1, c, line2);
+            let change = step.changes.last().unwrap();
+            let (y, x) = end_of(change.y, change.x, &change.inserted);
+            self.set_position(y, x);
+        }
+    }
+    //選択範囲、なければカーソルを囲む構文木の節を選択する。繰り返すと外側の節に広げる
+    #[cfg(feature = "tree-sitter")]
+    fn select_node(&mut self) {
+        self.history.seal();
+        let (start, end) = self
+            .selection()
+            .unwrap_or((self.position(), self.position()));
+        let Some(tree) = self.highlighter.tree() else {
+            self.msg = String::from("構文木がありません");
+            return;
+        };
+        if let Some(((sy, sx), (ey, ex))) = tree.enclosing(start, end) {
+            let mut anchor = Cursor::new();
+            anchor.y = sy;
+            anchor.x = str_width(&self.buffer.line(sy)[..sx]);
+            self.anchor = Some(anchor);
+            self.set_position(ey, ex);
+        }
+    }
+    //カーソルを親の節、または次の兄弟の節の先頭へ動かす
+    #[cfg(feature = "tree-sitter")]
+    fn jump_node(&mut self, parent: bool) {
+        self.history.seal();
+        self.anchor = None;
+        let Some(tree) = self.highlighter.tree() else {
+            self.msg = String::from("構文木がありません");
+            return;
+        };
+        let position = if parent {
+            tree.parent(self.position())
         } else {
-            self.buffer[self.cursor.y] = format!("{}{}", c, self.line);
+            tree.next_sibling(self.position())
+        };
+        if let Some((y, x)) = position {
+            self.set_position(y, x);
         }
-        self.cursor.x += UnicodeWidthChar::width(c).unwrap();
     }
-    fn move_up(&mut self) {
-        self.cursor.y -= 1;
-        if self.cursor.y < self.buffer_offset {
-            self.buffer_offset -= 1;
+    pub fn view(&self) -> (Cursor, usize) {
+        return (self.cursor.clone(), self.buffer_offset);
+    }
+    //別の画面で編集されて行が減っていることがあるので、カーソルをバッファの中に収める
+    pub fn set_view(&mut self, view: (Cursor, usize)) {
+        (self.cursor, self.buffer_offset) = view;
+        let max = self.buffer.len_lines() - 1;
+        self.cursor.y = self.cursor.y.min(max);
+        self.buffer_offset = self.buffer_offset.min(self.cursor.y);
+        self.line = self.buffer.line(self.cursor.y).to_string();
+        self.cursor.x = self.cursor.x.min(str_width(&self.line));
+    }
+    //endまでの編集を1回のundoで戻せるようにまとめる
+    pub fn begin(&mut self) {
+        self.history.begin(&self.cursor);
+    }
+    pub fn end(&mut self) {
+        self.history.end();
+    }
+    //カーソル位置の一致をreplacementに置き換えて、カーソルを置き換えた文字列の後ろに移す
+    //expandならreplacementの$1などをキャプチャした文字列に展開する。置き換える前と後のバイト数を返す
+    pub fn replace(
+        &mut self,
+        regex: &Regex,
+        replacement: &str,
+        expand: bool,
+    ) -> Option<(usize, usize)> {
+        let (y, x) = self.position();
+        let line = self.buffer.line(y).to_string();
+        let caps = regex.captures_at(&line, x)?;
+        let m = caps.get(0).unwrap();
+        if m.start() != x {
+            return None;
         }
+        let removed = m.as_str().to_string();
+        let mut inserted = String::new();
+        if expand {
+            caps.expand(replacement, &mut inserted);
+        } else {
+            inserted.push_str(replacement);
+        }
+        self.edit(y, x, &removed, &inserted, Kind::Edit);
+        self.cursor.x += str_width(&inserted);
+        return Some((removed.len(), inserted.len()));
     }
-    fn move_down(&mut self) {
-        self.cursor.y += 1;
-        let mut y = 0;
-        let mut i = self.buffer_offset;
-        let lines = self.buffer.len().to_string().len();
-        let width = self.width as usize - lines - 1;
-        while i < self.cursor.y {
-            let mut line = self.buffer[i].clone();
-            while width < line.width() {
-                let mut w = String::new();
-                for c in line.chars() {
-                    if w.width() + c.width().unwrap() > width {
-                        break;
-                    }
-                    w.push(c);
-                }
-                line = line[w.len()..].to_string();
-                if i < self.cursor.y {
-                    y += 1;
+    //カーソルを(行, バイト位置)に移して画面内に入れる
+    fn set_position(&mut self, y: usize, x: usize) {
+        self.cursor.y = y;
+        self.cursor.x = str_width(&self.buffer.line(y)[..x]);
+        self.line = self.buffer.line(y).to_string();
+        self.scroll_to_cursor();
+    }
+    pub fn position(&self) -> (usize, usize) {
+        let y = self.cursor.y;
+        return (y, byte_index(&self.buffer.line(y), self.cursor.x));
+    }
+    pub fn count(&self, regex: &Regex) -> usize {
+        return self
+            .buffer
+            .lines()
+            .map(|line| regex.find_iter(&line).count())
+            .sum();
+    }
+    //カーソル位置からregexを探してカーソルを移動する。skipならカーソル位置の一致を飛ばす
+    //見つからなければNone、末尾(先頭)から折り返した場合はSome(true)を返す
+    pub fn find(&mut self, regex: &Regex, forward: bool, skip: bool) -> Option<bool> {
+        let y = self.cursor.y;
+        let x = byte_index(&self.buffer.line(y), self.cursor.x);
+        let l = self.buffer.len_lines();
+        let mut found = None;
+        for n in 0..=l {
+            let i = if forward {
+                (y + n) % l
+            } else {
+                (y + l - n) % l
+            };
+            let line = self.buffer.line(i);
+            let mut m = regex.find_iter(&line).map(|m| m.start());
+            let j = if n != 0 {
+                if forward {
+                    m.next()
+                } else {
+                    m.last()
                 }
+            } else if forward {
+                m.find(|&j| x < j || (x == j && !skip))
+            } else {
+                m.filter(|&j| j < x).last()
+            };
+            if let Some(j) = j {
+                let wrapped = if forward { l <= y + n } else { y < n };
+                found = Some((i, j, wrapped));
+                break;
             }
-            y += 1;
-            i += 1;
         }
-        let mut len = 0;
-        let mut line = self.buffer[self.cursor.y].clone();
-        while width < line.width() {
-            let mut w = String::new();
-            for c in line.chars() {
-                if w.width() + c.width().unwrap() > width {
-                    break;
-                }
-                w.push(c);
-            }
-            line = line[w.len()..].to_string();
-            len += 1;
+        let (i, j, wrapped) = found?;
+        self.cursor.y = i;
+        self.cursor.x = str_width(&self.buffer.line(i)[..j]);
+        self.line = self.buffer.line(i).to_string();
+        self.scroll_to_cursor();
+        return Some(wrapped);
+    }
+    //折り返しを含めた行iの表示行数
+    fn rows(&self, i: usize) -> usize {
+        return self.chunks(&self.buffer.line(i)).len();
+    }
+    //カーソルが画面内に入るようにbuffer_offsetを調整する
+    fn scroll_to_cursor(&mut self) {
+        let height = (self.height as usize).saturating_sub(1);
+        if self.cursor.y < self.buffer_offset {
+            self.buffer_offset = self.cursor.y;
+        }
+        //1行は少なくとも1行分の高さがあるので、画面の高さより離れた行は数えずに飛ばす
+        if self.buffer_offset + height < self.cursor.y {
+            self.buffer_offset = self.cursor.y - height;
         }
-        len += 1;
-        y = y + len - 1;
-        if y >= self.height - 1 {
+        let mut y = (self.buffer_offset..=self.cursor.y)
+            .map(|i| self.rows(i))
+            .sum::<usize>();
+        while self.buffer_offset < self.cursor.y && y > height {
+            y -= self.rows(self.buffer_offset);
             self.buffer_offset += 1;
         }
     }
+    fn move_up(&mut self) {
+        self.cursor.y -= 1;
+        if self.cursor.y < self.buffer_offset {
+            self.buffer_offset -= 1;
+        }
+    }
+    fn move_down(&mut self) {
+        self.cursor.y += 1;
+        self.scroll_to_cursor();
+    }
     fn up(&mut self) {
         if 0 < self.cursor.y {
             self.move_up();
-            let str = &*self.buffer[self.cursor.y].clone();
-            let size = UnicodeWidthStr::width(str);
+            let str = &*self.buffer.line(self.cursor.y);
+            let size = str_width(str);
             if size == 0 || size - 1 < self.cursor.x {
                 self.cursor.x = size;
             }
@@ -362,13 +1087,13 @@ impl Normal {
     fn down(&mut self) {
         if self.cursor.y < self.max {
             self.move_down();
-            let str = &*self.buffer[self.cursor.y].clone();
-            let size = UnicodeWidthStr::width(str);
+            let str = &*self.buffer.line(self.cursor.y);
+            let size = str_width(str);
             if size == 0 || size - 1 < self.cursor.x {
                 self.cursor.x = size;
             }
         } else {
-            self.cursor.x = UnicodeWidthStr::width(&*self.line);
+            self.cursor.x = str_width(&self.line);
         }
     }
     fn left(&mut self) {
@@ -376,7 +1101,7 @@ impl Normal {
             let mut w = 0;
             let mut s = 0;
             for c in self.line.chars() {
-                s = c.width().unwrap();
+                s = char_width(c);
                 w += s;
                 if w >= self.cursor.x {
                     break;
@@ -385,15 +1110,15 @@ impl Normal {
             self.cursor.x -= s;
         } else if 0 < self.cursor.y {
             self.move_up();
-            self.cursor.x = self.buffer[self.cursor.y].width();
+            self.cursor.x = str_width(&self.buffer.line(self.cursor.y));
         }
     }
     fn right(&mut self) {
-        if self.cursor.x < UnicodeWidthStr::width(&*self.line) {
+        if self.cursor.x < str_width(&self.line) {
             let mut w = 0;
             let mut s = 0;
             for c in self.line.chars() {
-                s = c.width().unwrap();
+                s = char_width(c);
                 w += s;
                 if w > self.cursor.x {
                     break;
@@ -406,3 +1131,75 @@ impl Normal {
         }
     }
 }
+
+//表示幅xの位置に対応する行内のバイト位置
+fn byte_index(line: &str, x: usize) -> usize {
+    let mut w = 0;
+    for (i, c) in line.char_indices() {
+        if w >= x {
+            return i;
+        }
+        w += char_width(c);
+    }
+    return line.len();
+}
+
+//文字の表示幅。タブはtab_width列の空白、その他の制御文字は^Aのように2列で描く
+fn char_width(c: char) -> usize {
+    if c == '\t' {
+        return config::current().tab_width;
+    }
+    return c.width().unwrap_or(2);
+}
+
+fn str_width(s: &str) -> usize {
+    return s.chars().map(char_width).sum();
+}
+
+//文字を画面に描くときの文字列。幅はchar_widthと同じになる
+fn display(c: char) -> String {
+    if c == '\t' {
+        return " ".repeat(char_width(c));
+    }
+    if c.width().is_some() {
+        return c.to_string();
+    }
+    if c.is_ascii() {
+        return format!("^{}", (c as u8 ^ 0x40) as char);
+    }
+    return String::from("^?");
+}
+
+//(y, x)にtextを入力したときに、textの末尾が来る(行, バイト位置)
+fn end_of(y: usize, x: usize, text: &str) -> (usize, usize) {
+    return match text.rfind('\n') {
+        Some(i) => (y + text.matches('\n').count(), text.len() - i - 1),
+        None => (y, x + text.len()),
+    };
+}
+
+//本文の幅に収まるように行を折り返す
+fn wrap(line: &str, width: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut w = 0;
+    for (i, c) in line.char_indices() {
+        let cw = char_width(c);
+        if width < w + cw && start < i {
+            chunks.push(&line[start..i]);
+            start = i;
+            w = 0;
+        }
+        w += cw;
+    }
+    chunks.push(&line[start..]);
+    return chunks;
+}
+
+//画面の一番上のタブ行を左クリックしたら、その列のタブに切り替えるようmainに頼む
+pub fn click(mouse: MouseEvent) -> Action {
+    if mouse.kind == MouseEventKind::Down(MouseButton::Left) && mouse.row == 0 {
+        return Action::Tab(mouse.column);
+    }
+    return Action::None;
+}
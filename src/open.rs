@@ -1,17 +1,25 @@
-use crate::{Dir, Mode};
+use crate::{
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    theme, Dir, Mode,
+};
 use crossterm::{
     cursor::{MoveTo, Show},
-    event::{read, Event, KeyCode, KeyModifiers},
+    event::Event,
     style::{Color, SetForegroundColor},
     terminal::{window_size, Clear, ClearType},
 };
 use std::io::{Stdout, Write};
 
-pub struct Open {}
+pub struct Open {
+    chord: Chord,
+}
 
 impl Open {
     pub fn new() -> Self {
-        return Self {};
+        let chord = Chord::new();
+        return Self { chord };
     }
     pub fn run(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &mut Dir) -> String {
         self.output(stdout, dir);
@@ -25,36 +33,36 @@ impl Open {
             stdout,
             "{}{}[ファイルを開く]{}",
             MoveTo(0, 0),
-            SetForegroundColor(Color::Rgb {
-                r: 0,
-                g: 255,
-                b: 255
-            }),
+            SetForegroundColor(theme::current().header),
             SetForegroundColor(Color::Reset)
         )
         .unwrap();
         dir.output(stdout, true, 1);
         let path = dir.dir_path.to_str().unwrap();
-        write!(stdout, "{}{}", MoveTo(0, height - 1), path).unwrap();
+        write!(
+            stdout,
+            "{}{} {}",
+            MoveTo(0, height - 1),
+            path,
+            self.chord.pending()
+        )
+        .unwrap();
         stdout.flush().unwrap();
     }
     fn input(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &mut Dir) -> String {
         let mut path = String::new();
-        if let Ok(Event::Key(key)) = read() {
-            match key.code {
-                KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
-                    self.back(stdout, mode)
-                }
-                KeyCode::Enter => path = self.select(stdout, mode, dir),
-                KeyCode::Up => {
-                    if 0 < dir.cursor.y {
-                        dir.cursor.y -= 1;
-                    }
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let input = self.chord.input(&config::current().keymaps.open, key);
+            match input {
+                Input::Command(Command::Cancel) => self.back(stdout, mode),
+                Input::Command(Command::Select) => path = self.select(stdout, mode, dir),
+                Input::Command(Command::MoveUp) if 0 < dir.cursor.y => {
+                    dir.cursor.y -= 1;
                 }
-                KeyCode::Down => {
-                    if dir.cursor.y < dir.dirs.len() + dir.files.len() {
-                        dir.cursor.y += 1;
-                    }
+                Input::Command(Command::MoveDown)
+                    if dir.cursor.y < dir.dirs.len() + dir.files.len() =>
+                {
+                    dir.cursor.y += 1;
                 }
                 _ => {}
             }
@@ -0,0 +1,99 @@
+use crate::{
+    command::Command,
+    config,
+    file::encodings,
+    keys::{self, Chord, Input},
+    screen::{Screen, Style},
+    Dir, Mode, Normal,
+};
+use crossterm::event::Event;
+use encoding_rs::Encoding;
+use std::io::Stdout;
+
+//ファイルを開き直す文字コードを、そのファイルを読めるものの中からステータス行で選ぶ
+pub struct Reopen {
+    encodings: Vec<&'static Encoding>,
+    index: usize,
+    chord: Chord,
+}
+
+impl Reopen {
+    pub fn new() -> Self {
+        let encodings = Vec::new();
+        let index = 0;
+        let chord = Chord::new();
+        return Self {
+            encodings,
+            index,
+            chord,
+        };
+    }
+    //今の文字コード以外で読めるものを候補にする。候補がなければ選ばずに戻る
+    pub fn start(&mut self, normal: &mut Normal, mode: &mut Mode, path: &str) {
+        self.index = 0;
+        self.encodings = match encodings(path) {
+            Ok(encodings) => encodings,
+            Err(e) => {
+                normal.msg = e.to_string();
+                *mode = Mode::Normal;
+                return;
+            }
+        };
+        self.encodings.retain(|&e| e != normal.buffer.encoding);
+        if self.encodings.is_empty() {
+            normal.msg = String::from("他の文字コードでは読めません");
+            *mode = Mode::Normal;
+        }
+    }
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        screen: &mut Screen,
+        mode: &mut Mode,
+        normal: &mut Normal,
+        dir: &Dir,
+    ) {
+        self.output(stdout, screen, normal, dir);
+        self.input(mode, normal, dir);
+    }
+    fn output(&mut self, stdout: &mut Stdout, screen: &mut Screen, normal: &mut Normal, dir: &Dir) {
+        normal.output(screen, &dir.path);
+        let y = screen.height.saturating_sub(1);
+        screen.clear_line(0, y);
+        let prompt = "開き直す文字コード(←→で選んでEnter) ";
+        let mut x = screen.put(0, y, prompt, Style::new());
+        let mut cursor = x;
+        for (i, encoding) in self.encodings.iter().enumerate() {
+            let mut style = Style::new();
+            if i == self.index {
+                style.reverse = true;
+                cursor = x;
+            }
+            x = screen.put(x, y, encoding.name(), style);
+            x = screen.put(x, y, " ", Style::new());
+        }
+        screen.put(x, y, &self.chord.pending(), Style::new());
+        screen.set_cursor(cursor.min(screen.width.saturating_sub(1)), y);
+        screen.flush(stdout);
+    }
+    fn input(&mut self, mode: &mut Mode, normal: &mut Normal, dir: &Dir) {
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let input = self.chord.input(&config::current().keymaps.reopen, key);
+            match input {
+                Input::Command(Command::Cancel) => *mode = Mode::Normal,
+                Input::Command(Command::Select) => {
+                    normal.reopen(&dir.path, self.encodings[self.index]);
+                    *mode = Mode::Normal;
+                }
+                Input::Command(Command::MoveLeft) => {
+                    let len = self.encodings.len();
+                    self.index = (self.index + len - 1) % len;
+                }
+                Input::Command(Command::MoveRight) => {
+                    self.index = (self.index + 1) % self.encodings.len();
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crossterm::{
+    cursor::MoveTo,
+    style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor},
+};
+use std::{
+    fmt::Write as _,
+    io::{Stdout, Write},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub reverse: bool,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        return Self {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            reverse: false,
+        };
+    }
+    pub fn fg(color: Color) -> Self {
+        let mut style = Self::new();
+        style.fg = color;
+        return style;
+    }
+}
+
+//textが空のセルは左隣の全角文字の右半分
+#[derive(Clone, PartialEq)]
+struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        return Self {
+            text: String::from(" "),
+            style: Style::new(),
+        };
+    }
+}
+
+//画面に描く内容を持ち、前回描いた内容と違うセルだけを端末に出力する
+//width、heightと描く位置は、set_areaで決めた範囲(分割した画面の1つ)の中で数える
+pub struct Screen {
+    pub width: u16,
+    pub height: u16,
+    origin: (u16, u16),
+    size: (u16, u16),
+    cells: Vec<Cell>,
+    old: Vec<Option<Cell>>,
+    cursor: (u16, u16),
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        let width = 0;
+        let height = 0;
+        let origin = (0, 0);
+        let size = (0, 0);
+        let cells = Vec::new();
+        let old = Vec::new();
+        let cursor = (0, 0);
+        return Self {
+            width,
+            height,
+            origin,
+            size,
+            cells,
+            old,
+            cursor,
+        };
+    }
+    //端末の大きさに合わせ、描く範囲を画面全体に戻す
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if (width, height) != self.size {
+            self.size = (width, height);
+            self.cells = vec![Cell::blank(); width as usize * height as usize];
+            self.invalidate();
+        }
+        self.set_area(0, 0, width, height);
+    }
+    //(x, y)から幅width、高さheightの範囲に描くようにする
+    pub fn set_area(&mut self, x: u16, y: u16, width: u16, height: u16) {
+        self.origin = (x, y);
+        self.width = width;
+        self.height = height;
+    }
+    //端末の表示が分からなくなったとき(他のモードが画面を消したときなど)に全体を描き直させる
+    pub fn invalidate(&mut self) {
+        self.old = vec![None; self.cells.len()];
+    }
+    //y行目のx列目以降を空白にする
+    pub fn clear_line(&mut self, x: u16, y: u16) {
+        if self.height <= y {
+            return;
+        }
+        for i in x..self.width {
+            let i = self.index(i, y);
+            self.set(i, String::from(" "), Style::new());
+        }
+    }
+    //(x, y)からtextを書き、書き終わった次の列を返す。画面の右端で切る
+    pub fn put(&mut self, x: u16, y: u16, text: &str, style: Style) -> u16 {
+        let mut x = x;
+        if self.height <= y {
+            return x;
+        }
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0) as u16;
+            if w == 0 {
+                continue;
+            }
+            if self.width < x + w {
+                if x < self.width {
+                    let i = self.index(x, y);
+                    self.set(i, String::from(" "), style);
+                }
+                return self.width;
+            }
+            let i = self.index(x, y);
+            self.set(i, c.to_string(), style);
+            if w == 2 {
+                self.set(i + 1, String::new(), style);
+            }
+            x += w;
+        }
+        return x;
+    }
+    //全角文字の片側だけを上書きするときは、残る半分を空白にする。範囲の外には触らない
+    fn set(&mut self, i: usize, text: String, style: Style) {
+        let x = (i % self.size.0 as usize) as u16;
+        if !text.is_empty() && self.cells[i].text.is_empty() && self.origin.0 < x {
+            self.cells[i - 1] = Cell::blank();
+        }
+        if self.cells[i].text.width() == 2 && x + 1 < self.origin.0 + self.width {
+            self.cells[i + 1] = Cell::blank();
+        }
+        self.cells[i] = Cell { text, style };
+    }
+    pub fn set_cursor(&mut self, x: u16, y: u16) {
+        self.cursor = (self.origin.0 + x, self.origin.1 + y);
+    }
+    pub fn flush(&mut self, stdout: &mut Stdout) {
+        let mut output = String::new();
+        let mut style = None;
+        let (width, height) = self.size;
+        for y in 0..height {
+            let mut x = 0;
+            let mut moved = false;
+            while x < width {
+                let i = y as usize * width as usize + x as usize;
+                if self.old[i].as_ref() == Some(&self.cells[i]) {
+                    x += 1;
+                    moved = false;
+                    continue;
+                }
+                //全角文字の右半分だけが変わったときは左半分から書き直す
+                let mut i = i;
+                let mut start = x;
+                if self.cells[i].text.is_empty() && 0 < x {
+                    i -= 1;
+                    start -= 1;
+                    moved = false;
+                }
+                if !moved {
+                    write!(output, "{}", MoveTo(start, y)).unwrap();
+                    moved = true;
+                }
+                let cell = &self.cells[i];
+                if style != Some(cell.style) {
+                    set_style(&mut output, &cell.style);
+                    style = Some(cell.style);
+                }
+                output.push_str(&cell.text);
+                let w = cell
+                    .text
+                    .chars()
+                    .next()
+                    .map_or(1, |c| c.width().unwrap_or(1));
+                for j in i..i + w.max(1) {
+                    self.old[j] = Some(self.cells[j].clone());
+                }
+                x = start + w.max(1) as u16;
+            }
+        }
+        if style.is_some() {
+            write!(output, "{}", SetAttribute(Attribute::Reset)).unwrap();
+        }
+        write!(stdout, "{}{}", output, MoveTo(self.cursor.0, self.cursor.1)).unwrap();
+        stdout.flush().unwrap();
+    }
+    fn index(&self, x: u16, y: u16) -> usize {
+        let (x, y) = (self.origin.0 + x, self.origin.1 + y);
+        return y as usize * self.size.0 as usize + x as usize;
+    }
+}
+
+//outputの後ろにstyleに切り替える出力を足す
+fn set_style(output: &mut String, style: &Style) {
+    write!(
+        output,
+        "{}{}{}",
+        SetAttribute(Attribute::Reset),
+        SetForegroundColor(style.fg),
+        SetBackgroundColor(style.bg)
+    )
+    .unwrap();
+    if style.reverse {
+        write!(output, "{}", SetAttribute(Attribute::Reverse)).unwrap();
+    }
+}
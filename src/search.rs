@@ -0,0 +1,388 @@
+use crate::{
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    screen::{Screen, Style},
+    theme, Cursor, Dir, Mode, Normal,
+};
+use crossterm::event::{Event, KeyCode};
+use regex::{Regex, RegexBuilder};
+use std::io::Stdout;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub struct Search {
+    cursor: Cursor,
+    input: String,
+    query: String,
+    replacement: String,
+    origin: (Cursor, usize),
+    msg: String,
+    input_mode: InputMode,
+    replace: bool,
+    count: usize,
+    total: Option<usize>,
+    start: (usize, usize),
+    passed: bool,
+    pattern: Option<Regex>,
+    regex: bool,
+    ignore_case: bool,
+    word: bool,
+    chord: Chord,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        let cursor = Cursor::new();
+        let input = String::new();
+        let query = String::new();
+        let replacement = String::new();
+        let origin = (Cursor::new(), 0);
+        let msg = String::new();
+        let input_mode = InputMode::Find;
+        let replace = false;
+        let count = 0;
+        let total = None;
+        let start = (0, 0);
+        let passed = false;
+        let pattern = None;
+        let regex = false;
+        let ignore_case = false;
+        let word = false;
+        let chord = Chord::new();
+        return Self {
+            cursor,
+            input,
+            query,
+            replacement,
+            origin,
+            msg,
+            input_mode,
+            replace,
+            count,
+            total,
+            start,
+            passed,
+            pattern,
+            regex,
+            ignore_case,
+            word,
+            chord,
+        };
+    }
+    //検索を始める。前回の検索文字列を残したまま、現在のカーソル位置を起点にする
+    pub fn start(&mut self, normal: &mut Normal, replace: bool) {
+        self.replace = replace;
+        self.input_mode = InputMode::Find;
+        self.origin = normal.view();
+        self.input = self.query.clone();
+        self.cursor.x = self.input.width();
+        self.msg = String::new();
+        self.compile();
+        self.total = self.pattern.as_ref().map(|regex| normal.count(regex));
+        normal.highlight = self.pattern.clone();
+    }
+    pub fn run(
+        &mut self,
+        stdout: &mut Stdout,
+        screen: &mut Screen,
+        mode: &mut Mode,
+        normal: &mut Normal,
+        dir: &Dir,
+    ) {
+        self.output(stdout, screen, normal, dir);
+        self.input(mode, normal);
+    }
+    fn output(&mut self, stdout: &mut Stdout, screen: &mut Screen, normal: &mut Normal, dir: &Dir) {
+        normal.output(screen, &dir.path);
+        let height = screen.height;
+        let prompt = match self.input_mode {
+            InputMode::Find if self.replace => "置換 検索 > ",
+            InputMode::Find => "検索 > ",
+            InputMode::Replace => "置換後 > ",
+            InputMode::Check => "置換しますか？(y/n/a/q) ",
+        };
+        let count = match self.total {
+            Some(total) if !matches!(self.input_mode, InputMode::Check) => {
+                format!(" [{}件]", total)
+            }
+            _ => String::new(),
+        };
+        let mut options = Vec::new();
+        if self.regex {
+            options.push("正規表現");
+        }
+        if self.ignore_case {
+            options.push("大小区別なし");
+        }
+        if self.word {
+            options.push("単語単位");
+        }
+        let options = if options.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", options.join(" "))
+        };
+        let input = if matches!(self.input_mode, InputMode::Check) {
+            ""
+        } else {
+            &self.input
+        };
+        let y = height.saturating_sub(1);
+        screen.clear_line(0, y);
+        let text = format!("{}{}{}{} ", prompt, input, count, options);
+        let x = screen.put(0, y, &text, Style::new());
+        let warning = Style::fg(theme::current().warning);
+        let x = screen.put(x, y, &self.msg, warning);
+        screen.put(x, y, &format!(" {}", self.chord.pending()), Style::new());
+        screen.set_cursor((prompt.width() + self.cursor.x) as u16, y);
+        screen.flush(stdout);
+    }
+    fn input(&mut self, mode: &mut Mode, normal: &mut Normal) {
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let check = matches!(self.input_mode, InputMode::Check);
+            let find = matches!(self.input_mode, InputMode::Find);
+            //置換するか聞いているときは、答えを割り当てたreplaceの割り当てを使う
+            let input = if check {
+                self.chord.input(&config::current().keymaps.replace, key)
+            } else {
+                self.chord.input(&config::current().keymaps.search, key)
+            };
+            match input {
+                Input::Command(Command::Cancel) if check => self.finish(mode, normal),
+                Input::Command(Command::Cancel) => {
+                    normal.set_view(self.origin.clone());
+                    self.back(mode, normal);
+                }
+                Input::Command(Command::ReplaceMatch) => {
+                    let skip = self.replace_one(normal);
+                    self.next_match(mode, normal, skip);
+                }
+                Input::Command(Command::SkipMatch) => self.next_match(mode, normal, true),
+                Input::Command(Command::ReplaceAll) => {
+                    while matches!(self.input_mode, InputMode::Check) {
+                        let skip = self.replace_one(normal);
+                        self.next_match(mode, normal, skip);
+                    }
+                }
+                Input::Unbound(name) => self.msg = format!("{}は割り当てられていません", name),
+                _ if check => {}
+                Input::Command(Command::ToggleRegex) => {
+                    self.regex = !self.regex;
+                    self.refresh(normal);
+                }
+                Input::Command(Command::ToggleIgnoreCase) => {
+                    self.ignore_case = !self.ignore_case;
+                    self.refresh(normal);
+                }
+                Input::Command(Command::ToggleWord) => {
+                    self.word = !self.word;
+                    self.refresh(normal);
+                }
+                Input::Command(Command::Select) => self.enter(mode, normal),
+                Input::Command(Command::NextMatch) if find => self.next(normal, true),
+                Input::Command(Command::PreviousMatch) if find => self.next(normal, false),
+                Input::Command(Command::MoveLeft) => self.left(),
+                Input::Command(Command::MoveRight) => self.right(),
+                Input::Command(Command::DeleteBackward) => {
+                    self.delete();
+                    self.incremental(normal);
+                }
+                Input::Key(key) => {
+                    if let KeyCode::Char(c) = key.code {
+                        self.typing(c);
+                        self.incremental(normal);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    fn enter(&mut self, mode: &mut Mode, normal: &mut Normal) {
+        match self.input_mode {
+            InputMode::Find if self.replace && self.pattern.is_some() => {
+                self.input_mode = InputMode::Replace;
+                self.input = self.replacement.clone();
+                self.cursor.x = self.input.width();
+            }
+            InputMode::Find => self.back(mode, normal),
+            InputMode::Replace => {
+                //起点から末尾まで進み、先頭に折り返して起点まで戻ったら終わる
+                normal.set_view(self.origin.clone());
+                self.start = normal.position();
+                self.passed = false;
+                self.count = 0;
+                self.msg = String::new();
+                self.input_mode = InputMode::Check;
+                self.cursor.x = 0;
+                normal.begin();
+                self.next_match(mode, normal, false);
+            }
+            InputMode::Check => {}
+        }
+    }
+    //カーソル位置の一致を置き換える。次の検索でカーソル位置を飛ばす必要があればtrueを返す
+    fn replace_one(&mut self, normal: &mut Normal) -> bool {
+        let regex = self.pattern.clone().unwrap();
+        let (y, x) = normal.position();
+        match normal.replace(&regex, &self.replacement, self.regex) {
+            Some((removed, inserted)) => {
+                if y == self.start.0 && x < self.start.1 {
+                    self.start.1 = (self.start.1 + inserted).saturating_sub(removed);
+                }
+                self.count += 1;
+                return removed == 0;
+            }
+            None => return true,
+        }
+    }
+    fn next_match(&mut self, mode: &mut Mode, normal: &mut Normal, skip: bool) {
+        let regex = self.pattern.clone().unwrap();
+        match normal.find(&regex, true, skip) {
+            Some(wrapped) => {
+                self.passed |= wrapped;
+                if self.passed && self.start <= normal.position() {
+                    self.finish(mode, normal);
+                }
+            }
+            None => self.finish(mode, normal),
+        }
+    }
+    fn finish(&mut self, mode: &mut Mode, normal: &mut Normal) {
+        normal.end();
+        normal.msg = format!("{}件置換しました", self.count);
+        self.back(mode, normal);
+    }
+    //検索文字列が変わったら起点から探し直す
+    fn incremental(&mut self, normal: &mut Normal) {
+        if matches!(self.input_mode, InputMode::Replace) {
+            self.replacement = self.input.clone();
+            return;
+        }
+        self.query = self.input.clone();
+        self.refresh(normal);
+    }
+    fn refresh(&mut self, normal: &mut Normal) {
+        normal.set_view(self.origin.clone());
+        self.compile();
+        self.total = self.pattern.as_ref().map(|regex| normal.count(regex));
+        normal.highlight = self.pattern.clone();
+        if let Some(regex) = &self.pattern {
+            let found = normal.find(regex, true, false);
+            self.set_msg(found);
+        }
+    }
+    fn next(&mut self, normal: &mut Normal, forward: bool) {
+        if let Some(regex) = &self.pattern {
+            let found = normal.find(regex, forward, true);
+            self.set_msg(found);
+        }
+    }
+    //検索文字列と設定から正規表現を作る。作れなければエラーをmsgに表示する
+    fn compile(&mut self) {
+        self.pattern = None;
+        self.msg = String::new();
+        if self.query.is_empty() {
+            return;
+        }
+        let mut pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        if self.word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        match RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+        {
+            Ok(regex) => self.pattern = Some(regex),
+            Err(e) => {
+                let e = e.to_string();
+                self.msg = format!("正規表現のエラー: {}", e.lines().last().unwrap_or(""));
+            }
+        }
+    }
+    fn set_msg(&mut self, found: Option<bool>) {
+        self.msg = match found {
+            Some(true) => String::from("折り返しました"),
+            Some(false) => String::new(),
+            None => String::from("見つかりません"),
+        };
+    }
+    fn back(&mut self, mode: &mut Mode, normal: &mut Normal) {
+        self.input_mode = InputMode::Find;
+        normal.highlight = None;
+        *mode = Mode::Normal;
+    }
+    fn delete(&mut self) {
+        let mut w = String::new();
+        let mut s = String::new();
+        for c in self.input.chars() {
+            w.push(c);
+            if w.width() >= self.cursor.x {
+                s = String::from(c);
+                break;
+            }
+        }
+        let l = w.len();
+        if 0 < self.cursor.x {
+            let input1 = &self.input[0..l - s.len()];
+            let input2 = &self.input[l..];
+            self.input = format!("{}{}", input1, input2);
+            self.cursor.x -= s.width();
+        }
+    }
+    fn left(&mut self) {
+        if 0 < self.cursor.x {
+            let mut w = 0;
+            let mut s = 0;
+            for c in self.input.chars() {
+                s = c.width().unwrap();
+                w += s;
+                if w >= self.cursor.x {
+                    break;
+                }
+            }
+            self.cursor.x -= s;
+        }
+    }
+    fn right(&mut self) {
+        if self.cursor.x < UnicodeWidthStr::width(&*self.input) {
+            let mut w = 0;
+            let mut s = 0;
+            for c in self.input.chars() {
+                s = c.width().unwrap();
+                w += s;
+                if w > self.cursor.x {
+                    break;
+                }
+            }
+            self.cursor.x += s;
+        }
+    }
+    fn typing(&mut self, c: char) {
+        if 0 < self.cursor.x {
+            let mut w = String::new();
+            for c in self.input.chars() {
+                w.push(c);
+                if w.width() >= self.cursor.x {
+                    break;
+                }
+            }
+            let l = w.len();
+            let input1 = &self.input[0..l];
+            let input2 = &self.input[l..];
+            self.input = format!("{}{}{}", input1, c, input2);
+        } else {
+            self.input = format!("{}{}", c, self.input);
+        }
+        self.cursor.x += c.width().unwrap();
+    }
+}
+
+enum InputMode {
+    Find,
+    Replace,
+    Check,
+}
@@ -0,0 +1,626 @@
+#[cfg(feature = "tree-sitter")]
+use crate::tree::Tree;
+use crate::Buffer;
+use std::path::Path;
+
+//色分けする字句の種類。色はテーマで決める
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Type,
+    Function,
+    //Markdownの見出しとTOMLの表の名前
+    Heading,
+    Emphasis,
+    Code,
+    //TOMLのキーとMarkdownのリンクの文字
+    Key,
+}
+
+//行頭での字句解析の状態。複数行にまたがるコメントや文字列の途中かどうか
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Normal,
+    //入れ子の深さ
+    Comment(usize),
+    //Language::stringsの何番目の区切りで始まった文字列か
+    String(usize),
+    //Markdownの```で囲んだコード
+    Fence,
+}
+
+//行の中の(開始バイト, 終了バイト, 種類)
+pub type Span = (usize, usize, Scope);
+
+//言語ごとの字句の決まり。tokenizeが1行を色分けし、行末での状態を返す
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    //(開始, 終了, 入れ子にできるか)
+    block_comment: Option<(&'static str, &'static str, bool)>,
+    //(区切り, 複数行にまたがれるか, \でエスケープできるか)。長い区切りを先に並べる
+    strings: &'static [(&'static str, bool, bool)],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    //大文字で始まる名前を型とみなす
+    capital_types: bool,
+    //'a'のような文字リテラルがある
+    chars: bool,
+    //name!(..)をマクロとして関数と同じ色にする
+    macros: bool,
+    //$nameを変数として型と同じ色にする
+    variables: bool,
+    tokenize: fn(&Language, &str, State) -> (Vec<Span>, State),
+}
+
+const RUST: Language = Language {
+    name: "Rust",
+    extensions: &["rs"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/", true)),
+    strings: &[("\"", true, true)],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ],
+    capital_types: true,
+    chars: true,
+    macros: true,
+    variables: false,
+    tokenize: code,
+};
+
+const TOML: Language = Language {
+    name: "TOML",
+    extensions: &["toml"],
+    line_comments: &["#"],
+    block_comment: None,
+    strings: &[
+        ("\"\"\"", true, true),
+        ("'''", true, false),
+        ("\"", false, true),
+        ("'", false, false),
+    ],
+    keywords: &["true", "false", "inf", "nan"],
+    types: &[],
+    capital_types: false,
+    chars: false,
+    macros: false,
+    variables: false,
+    tokenize: toml,
+};
+
+const MARKDOWN: Language = Language {
+    name: "Markdown",
+    extensions: &["md", "markdown"],
+    line_comments: &[],
+    block_comment: None,
+    strings: &[],
+    keywords: &[],
+    types: &[],
+    capital_types: false,
+    chars: false,
+    macros: false,
+    variables: false,
+    tokenize: markdown,
+};
+
+const PYTHON: Language = Language {
+    name: "Python",
+    extensions: &["py", "pyw"],
+    line_comments: &["#"],
+    block_comment: None,
+    strings: &[
+        ("\"\"\"", true, true),
+        ("'''", true, true),
+        ("\"", false, true),
+        ("'", false, true),
+    ],
+    keywords: &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "match", "case", "nonlocal", "not", "or", "pass",
+        "raise", "return", "try", "while", "with", "yield", "self",
+    ],
+    types: &[
+        "int",
+        "float",
+        "complex",
+        "str",
+        "bytes",
+        "bool",
+        "list",
+        "tuple",
+        "dict",
+        "set",
+        "frozenset",
+        "object",
+    ],
+    capital_types: true,
+    chars: false,
+    macros: false,
+    variables: false,
+    tokenize: code,
+};
+
+const SHELL: Language = Language {
+    name: "Shell",
+    extensions: &["sh", "bash", "zsh"],
+    line_comments: &["#"],
+    block_comment: None,
+    strings: &[("\"", true, true), ("'", true, false)],
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do", "done",
+        "in", "function", "return", "local", "export", "readonly", "break", "continue", "exit",
+        "select",
+    ],
+    types: &[],
+    capital_types: false,
+    chars: false,
+    macros: false,
+    variables: true,
+    tokenize: code,
+};
+
+const LANGUAGES: [&Language; 5] = [&RUST, &TOML, &MARKDOWN, &PYTHON, &SHELL];
+
+//拡張子から言語を選ぶ
+pub fn language(path: &str) -> Option<&'static Language> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    return LANGUAGES
+        .into_iter()
+        .find(|language| language.extensions.contains(&extension));
+}
+
+//表示する行の色分けを、行頭の状態を覚えながら必要な行だけ求める
+//編集した行より後ろの状態は編集前のものを残しておき、求め直した状態が一致したらそこから先は正しいとみなす
+pub struct Highlighter {
+    path: String,
+    language: Option<&'static Language>,
+    //行ごとの行頭の状態
+    states: Vec<State>,
+    //valid行目までは今の内容での状態
+    valid: usize,
+    //computed行目までは一度は求めた状態。validより後ろは編集前の値
+    computed: usize,
+    //edited行目までは編集された行なので、編集前の状態と比べない
+    edited: usize,
+    //構文木で色分けできる言語ではこちらを使う
+    #[cfg(feature = "tree-sitter")]
+    tree: Option<Tree>,
+}
+
+impl Highlighter {
+    pub fn new(lines: usize) -> Self {
+        let path = String::new();
+        let language = None;
+        let states = vec![State::Normal; lines];
+        let valid = 1;
+        let computed = 1;
+        let edited = 0;
+        return Self {
+            path,
+            language,
+            states,
+            valid,
+            computed,
+            edited,
+            #[cfg(feature = "tree-sitter")]
+            tree: None,
+        };
+    }
+    //ファイルの名前が変わったら言語を選び直す。言語が変わったときはtrue
+    pub fn set_path(&mut self, path: &str, lines: usize) -> bool {
+        if path == self.path {
+            return false;
+        }
+        self.path = path.to_string();
+        let language = language(path);
+        if language.map(|l| l.name) == self.language.map(|l| l.name) {
+            return false;
+        }
+        self.language = language;
+        self.reset(lines);
+        #[cfg(feature = "tree-sitter")]
+        {
+            self.tree = language.and_then(|l| Tree::new(l.name));
+        }
+        return true;
+    }
+    //バッファを入れ替えたときに全ての状態を捨てる
+    pub fn reset(&mut self, lines: usize) {
+        self.states = vec![State::Normal; lines];
+        self.valid = 1;
+        self.computed = 1;
+        self.edited = 0;
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree) = &mut self.tree {
+            tree.reset();
+        }
+    }
+    //y行目の後ろのremoved行をinserted行に置き換えた
+    pub fn edit(&mut self, y: usize, removed: usize, inserted: usize) {
+        let start = y + 1;
+        self.states
+            .splice(start..start + removed, vec![State::Normal; inserted]);
+        if start + removed < self.computed {
+            self.computed = self.computed + inserted - removed;
+        } else {
+            self.computed = self.computed.min(start);
+        }
+        self.valid = self.valid.min(start);
+        self.edited = self.edited.max(start + inserted);
+    }
+    //構文木にも(y, x)の編集を伝える
+    #[cfg(feature = "tree-sitter")]
+    pub fn edit_tree(
+        &mut self,
+        buffer: &Buffer,
+        y: usize,
+        x: usize,
+        removed: &str,
+        inserted: &str,
+    ) {
+        if let Some(tree) = &mut self.tree {
+            tree.edit(buffer.byte(y, x), (y, x), removed, inserted);
+        }
+    }
+    //構文木を解析し直し、色分けが変わった行の範囲を返す
+    #[cfg(feature = "tree-sitter")]
+    pub fn parse(&mut self, buffer: &Buffer) -> Option<(usize, usize)> {
+        return self.tree.as_mut()?.parse(buffer);
+    }
+    #[cfg(feature = "tree-sitter")]
+    pub fn tree(&self) -> Option<&Tree> {
+        return self.tree.as_ref();
+    }
+    //行iの行頭の状態を求め、前に描いたときから変わったかを返す
+    pub fn changed(&mut self, buffer: &Buffer, i: usize) -> bool {
+        if self.language.is_none() || i < self.valid {
+            return false;
+        }
+        #[cfg(feature = "tree-sitter")]
+        if self.tree.is_some() {
+            return false;
+        }
+        let old = self.states[i];
+        let computed = i < self.computed;
+        return self.state(buffer, i) != old || !computed;
+    }
+    //行iの色分け
+    pub fn spans(&mut self, buffer: &Buffer, i: usize) -> Vec<Span> {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree) = &self.tree {
+            return tree.spans(buffer, i);
+        }
+        let Some(language) = self.language else {
+            return Vec::new();
+        };
+        let state = self.state(buffer, i);
+        return (language.tokenize)(language, &buffer.line(i), state).0;
+    }
+    fn state(&mut self, buffer: &Buffer, i: usize) -> State {
+        let Some(language) = self.language else {
+            return State::Normal;
+        };
+        while self.valid <= i {
+            let k = self.valid;
+            let (_, state) = (language.tokenize)(language, &buffer.line(k - 1), self.states[k - 1]);
+            if self.edited <= k && k < self.computed && self.states[k] == state {
+                self.valid = self.computed;
+            } else {
+                self.states[k] = state;
+                self.valid += 1;
+                self.computed = self.computed.max(self.valid);
+            }
+        }
+        if self.computed <= self.valid {
+            self.edited = 0;
+        }
+        return self.states[i];
+    }
+}
+
+//Rust、Python、シェルのような、コメントと文字列と単語からなる言語の字句解析
+fn code(language: &Language, line: &str, state: State) -> (Vec<Span>, State) {
+    let mut spans = Vec::new();
+    let mut state = state;
+    let mut start = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        match state {
+            State::Comment(depth) => {
+                let (open, close, nested) = language.block_comment.unwrap();
+                if rest.starts_with(close) {
+                    i += close.len();
+                    if depth == 1 {
+                        spans.push((start, i, Scope::Comment));
+                        state = State::Normal;
+                    } else {
+                        state = State::Comment(depth - 1);
+                    }
+                } else if nested && rest.starts_with(open) {
+                    i += open.len();
+                    state = State::Comment(depth + 1);
+                } else {
+                    i += c.len_utf8();
+                }
+            }
+            State::String(k) => {
+                let (delimiter, _, escape) = language.strings[k];
+                if escape && c == '\\' {
+                    i += 1;
+                    i += rest[1..].chars().next().map_or(0, |c| c.len_utf8());
+                } else if rest.starts_with(delimiter) {
+                    i += delimiter.len();
+                    spans.push((start, i, Scope::String));
+                    state = State::Normal;
+                } else {
+                    i += c.len_utf8();
+                }
+            }
+            State::Normal | State::Fence => {
+                start = i;
+                let before = line[..i].chars().next_back();
+                if language.line_comments.iter().any(|s| rest.starts_with(s))
+                    //シェルの#は単語の途中ではコメントにならない
+                    && (!language.variables || before.is_none_or(|c| c.is_whitespace()))
+                {
+                    spans.push((i, line.len(), Scope::Comment));
+                    return (spans, State::Normal);
+                }
+                if let Some((open, _, _)) = language.block_comment {
+                    if rest.starts_with(open) {
+                        i += open.len();
+                        state = State::Comment(1);
+                        continue;
+                    }
+                }
+                if let Some(k) = language
+                    .strings
+                    .iter()
+                    .position(|(s, _, _)| rest.starts_with(s))
+                {
+                    i += language.strings[k].0.len();
+                    state = State::String(k);
+                    continue;
+                }
+                if language.chars && c == '\'' {
+                    if let Some(end) = char_literal(rest) {
+                        i += end;
+                        spans.push((start, i, Scope::String));
+                        continue;
+                    }
+                }
+                if language.variables && c == '$' {
+                    i += 1 + variable(&rest[1..]);
+                    spans.push((start, i, Scope::Type));
+                    continue;
+                }
+                if c.is_ascii_digit() && !before.is_some_and(is_word) {
+                    i += number(rest);
+                    spans.push((start, i, Scope::Number));
+                    continue;
+                }
+                if is_word(c) {
+                    let end = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    i += end;
+                    let next = line[i..].chars().next();
+                    let scope = if language.keywords.contains(&word) {
+                        Some(Scope::Keyword)
+                    } else if language.types.contains(&word)
+                        || language.capital_types && c.is_uppercase()
+                    {
+                        Some(Scope::Type)
+                    } else if next == Some('(') || language.macros && next == Some('!') {
+                        Some(Scope::Function)
+                    } else {
+                        None
+                    };
+                    if let Some(scope) = scope {
+                        spans.push((start, i, scope));
+                    }
+                    continue;
+                }
+                i += c.len_utf8();
+            }
+        }
+    }
+    //行末で閉じていないものは次の行へ続ける。1行で終わる文字列は行末で終える
+    match state {
+        State::Comment(_) => spans.push((start, line.len(), Scope::Comment)),
+        State::String(k) => {
+            spans.push((start, line.len(), Scope::String));
+            if !language.strings[k].1 {
+                state = State::Normal;
+            }
+        }
+        State::Normal | State::Fence => {}
+    }
+    return (spans, state);
+}
+
+fn is_word(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_';
+}
+
+//'a'や'\n'の長さ。'aのようなライフタイムはNone
+fn char_literal(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    let (_, c) = chars.next()?;
+    if c == '\\' {
+        return s[2..].find('\'').map(|i| i + 3);
+    }
+    let (i, c) = chars.next()?;
+    return (c == '\'').then_some(i + 1);
+}
+
+//数値の長さ。1..2の..は含めない
+fn number(s: &str) -> usize {
+    let mut end = 0;
+    for (i, c) in s.char_indices() {
+        if c == '.' && !s[i + 1..].starts_with('.') || is_word(c) {
+            end = i + 1;
+        } else {
+            break;
+        }
+    }
+    return end;
+}
+
+//$の後の変数名の長さ。${..}と$1や$?のような1文字の変数も含める
+fn variable(s: &str) -> usize {
+    if s.starts_with('{') {
+        return s.find('}').map_or(s.len(), |i| i + 1);
+    }
+    let end = s.find(|c| !is_word(c)).unwrap_or(s.len());
+    if 0 < end {
+        return end;
+    }
+    return s.chars().next().map_or(0, |c| c.len_utf8());
+}
+
+//TOMLは表の名前とキーを加える
+fn toml(language: &Language, line: &str, state: State) -> (Vec<Span>, State) {
+    let (mut spans, end) = code(language, line, state);
+    if state != State::Normal {
+        return (spans, end);
+    }
+    let indent = line.len() - line.trim_start().len();
+    if line.trim_start().starts_with('[') {
+        let end = line.rfind(']').map_or(line.len(), |i| i + 1);
+        spans.push((indent, end, Scope::Heading));
+    } else if let Some(i) = line.find('=') {
+        spans.insert(0, (indent, line[..i].trim_end().len(), Scope::Key));
+    }
+    return (spans, end);
+}
+
+//Markdownは行頭の記号で見出し、引用、リスト、コードを分け、行の中では`code`、*強調*、[リンク](url)を色分けする
+fn markdown(_: &Language, line: &str, state: State) -> (Vec<Span>, State) {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+    if state == State::Fence {
+        let state = if fence { State::Normal } else { State::Fence };
+        return (vec![(0, line.len(), Scope::Code)], state);
+    }
+    if fence {
+        return (vec![(0, line.len(), Scope::Code)], State::Fence);
+    }
+    if trimmed.starts_with('#') && indent < 4 {
+        return (vec![(0, line.len(), Scope::Heading)], State::Normal);
+    }
+    if trimmed.starts_with('>') {
+        return (vec![(0, line.len(), Scope::Comment)], State::Normal);
+    }
+    let mut spans = Vec::new();
+    let digits = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+    let marker = if trimmed.starts_with(['-', '*', '+']) {
+        1
+    } else if 0 < digits && trimmed[digits..].starts_with(['.', ')']) {
+        digits + 1
+    } else {
+        0
+    };
+    if 0 < marker && trimmed[marker..].starts_with(' ') {
+        spans.push((indent, indent + marker, Scope::Keyword));
+    }
+    let mut i = indent + marker;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        let len = match c {
+            '`' => rest[1..].find('`').map(|k| {
+                spans.push((i, i + k + 2, Scope::Code));
+                k + 2
+            }),
+            '*' | '_' => {
+                let delimiter = if rest[1..].starts_with(c) { 2 } else { 1 };
+                let close = &rest[..delimiter];
+                rest[delimiter..].find(close).filter(|&k| 0 < k).map(|k| {
+                    spans.push((i, i + k + delimiter * 2, Scope::Emphasis));
+                    k + delimiter * 2
+                })
+            }
+            '[' => rest.find("](").and_then(|k| {
+                let e = rest[k..].find(')')?;
+                spans.push((i, i + k + 1, Scope::Key));
+                spans.push((i + k + 1, i + k + e + 1, Scope::String));
+                Some(k + e + 1)
+            }),
+            _ => None,
+        };
+        i += len.unwrap_or(c.len_utf8());
+    }
+    return (spans, State::Normal);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust(lines: usize) -> Highlighter {
+        let mut highlighter = Highlighter::new(lines);
+        highlighter.language = language("a.rs");
+        return highlighter;
+    }
+
+    //すべての行の色分けを、新しく作ったHighlighterで求めたものと比べる
+    fn check(highlighter: &mut Highlighter, buffer: &Buffer) {
+        let mut fresh = rust(buffer.len_lines());
+        for i in 0..buffer.len_lines() {
+            assert!(
+                highlighter.spans(buffer, i) == fresh.spans(buffer, i),
+                "{}行目",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn incremental() {
+        let text = "fn main() {\n    let a = 1;\n    let b = \"s\";\n}\n// end\nfn f() {}";
+        let mut buffer = Buffer::from_str(text);
+        let mut highlighter = rust(buffer.len_lines());
+        check(&mut highlighter, &buffer);
+        //(行, バイト位置, 取り除く文字列, 入れる文字列)
+        let edits = [
+            (1, 4, "", "/*"),
+            (3, 1, "", " */"),
+            (1, 4, "/*", ""),
+            (2, 0, "", "x /* a\nb /* c */\n"),
+            (0, 0, "", "/*\n"),
+            (0, 0, "/*\n", ""),
+            (2, 0, "x /* a\nb /* c */\n", ""),
+            (3, 1, " */", ""),
+        ];
+        for (y, x, removed, inserted) in edits {
+            buffer.replace(y, x, removed, inserted);
+            highlighter.edit(
+                y,
+                removed.matches('\n').count(),
+                inserted.matches('\n').count(),
+            );
+            //描くときと同じく、編集した行の近くだけを先に求める
+            for i in y..(y + 2).min(buffer.len_lines()) {
+                highlighter.changed(&buffer, i);
+            }
+            check(&mut highlighter, &buffer);
+        }
+        assert_eq!(buffer.text((0, 0), (5, 9)), text);
+    }
+}
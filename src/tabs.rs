@@ -0,0 +1,102 @@
+use crate::screen::{Screen, Style};
+use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+//1つのタブに表示するファイル名の最大の幅
+const MAX_WIDTH: usize = 20;
+
+//画面の一番上の行に開いているファイルをタブとして並べる
+pub struct Tabs {
+    //描いたタブの(開始列, 終了列, バッファの番号)
+    positions: Vec<(u16, u16, usize)>,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        let positions = Vec::new();
+        return Self { positions };
+    }
+    //entriesは(パス, 変更済みか)。入りきらないときは今のタブが見える範囲だけを描き、端に<と>を付ける
+    pub fn output(&mut self, screen: &mut Screen, entries: &[(String, bool)], current: usize) {
+        let labels = entries
+            .iter()
+            .map(|(path, modified)| label(path, *modified))
+            .collect::<Vec<_>>();
+        let width = screen.width as usize;
+        let (start, end) = visible(&labels, current, width);
+        self.positions.clear();
+        screen.clear_line(0, 0);
+        let mut x = 0;
+        if 0 < start {
+            x = screen.put(x, 0, "<", Style::new());
+        }
+        for (i, label) in labels.iter().enumerate().take(end).skip(start) {
+            let mut style = Style::new();
+            style.reverse = i == current;
+            let next = screen.put(x, 0, label, style);
+            self.positions.push((x, next, i));
+            x = next;
+        }
+        if end < labels.len() {
+            screen.put(screen.width.saturating_sub(1), 0, ">", Style::new());
+        }
+    }
+    //x列目にあるタブの番号
+    pub fn find(&self, x: u16) -> Option<usize> {
+        return self
+            .positions
+            .iter()
+            .find(|&&(start, end, _)| start <= x && x < end)
+            .map(|&(_, _, i)| i);
+    }
+}
+
+//ファイル名だけを表示し、長い名前は…で切る
+fn label(path: &str, modified: bool) -> String {
+    let name = match Path::new(path).file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => String::from("無題"),
+    };
+    let mut name = name;
+    if MAX_WIDTH < name.width() {
+        let mut w = 0;
+        name = name
+            .chars()
+            .take_while(|c| {
+                w += c.width().unwrap_or(0);
+                w < MAX_WIDTH
+            })
+            .collect();
+        name.push('…');
+    }
+    let modified = if modified { "*" } else { "" };
+    return format!(" {}{} ", name, modified);
+}
+
+//widthに入る、currentを含むタブの範囲。currentから左右に交互に広げる
+fn visible(labels: &[String], current: usize, width: usize) -> (usize, usize) {
+    let total = labels.iter().map(|label| label.width()).sum::<usize>();
+    if total <= width {
+        return (0, labels.len());
+    }
+    //両端の<と>の分を空けておく
+    let width = width.saturating_sub(2);
+    let (mut start, mut end) = (current, current + 1);
+    let mut used = labels[current].width();
+    loop {
+        let mut grown = false;
+        if end < labels.len() && used + labels[end].width() <= width {
+            used += labels[end].width();
+            end += 1;
+            grown = true;
+        }
+        if 0 < start && used + labels[start - 1].width() <= width {
+            start -= 1;
+            used += labels[start].width();
+            grown = true;
+        }
+        if !grown {
+            return (start, end);
+        }
+    }
+}
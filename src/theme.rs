@@ -0,0 +1,255 @@
+use crate::{error::Error, syntax::Scope};
+use crossterm::style::Color;
+use std::{env::var, fs::read_to_string, sync::RwLock};
+use toml::{Table, Value};
+
+//色分けの種類の数。Scopeの順に並べる
+const SCOPES: usize = 10;
+const SCOPE_NAMES: [&str; SCOPES] = [
+    "comment", "string", "number", "keyword", "type", "function", "heading", "emphasis", "code",
+    "key",
+];
+
+//画面に使う色を役割ごとにまとめたもの
+#[derive(Clone, Copy)]
+pub struct Theme {
+    //行番号や16進表示のオフセット、画面の境目
+    pub gutter: Color,
+    //ファイル一覧のディレクトリ
+    pub directory: Color,
+    //[ファイルを開く]などの見出し
+    pub header: Color,
+    //上書きの確認や検索の失敗
+    pub warning: Color,
+    //保存画面のメッセージ
+    pub message: Color,
+    //選択範囲と検索に一致した部分の背景。Resetなら反転表示にする
+    pub selection: Color,
+    syntax: [Color; SCOPES],
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    return Color::Rgb { r, g, b };
+}
+
+pub const DARK: Theme = Theme {
+    gutter: rgb(127, 127, 127),
+    directory: rgb(135, 175, 175),
+    header: rgb(0, 255, 255),
+    warning: rgb(215, 135, 95),
+    message: rgb(255, 255, 255),
+    selection: Color::Reset,
+    syntax: [
+        rgb(127, 127, 127),
+        rgb(215, 175, 95),
+        rgb(215, 135, 175),
+        rgb(95, 175, 255),
+        rgb(95, 215, 175),
+        rgb(215, 215, 135),
+        rgb(255, 135, 95),
+        rgb(215, 135, 215),
+        rgb(135, 175, 135),
+        rgb(135, 175, 215),
+    ],
+};
+
+pub const LIGHT: Theme = Theme {
+    gutter: rgb(135, 135, 135),
+    directory: rgb(0, 95, 135),
+    header: rgb(0, 135, 175),
+    warning: rgb(175, 0, 0),
+    message: rgb(0, 0, 0),
+    selection: rgb(188, 208, 235),
+    syntax: [
+        rgb(128, 128, 128),
+        rgb(135, 95, 0),
+        rgb(135, 0, 135),
+        rgb(0, 0, 175),
+        rgb(0, 110, 110),
+        rgb(95, 95, 0),
+        rgb(175, 60, 0),
+        rgb(135, 0, 95),
+        rgb(0, 110, 0),
+        rgb(0, 80, 160),
+    ],
+};
+
+//今使っているテーマ。描くところはどこでもcurrentで読む
+static THEME: RwLock<Theme> = RwLock::new(DARK);
+
+pub fn current() -> Theme {
+    return *THEME.read().unwrap();
+}
+
+//端末の色数に合わせてから切り替える
+pub fn set(theme: Theme) {
+    *THEME.write().unwrap() = theme.fallback(depth());
+}
+
+impl Theme {
+    pub fn scope(&self, scope: Scope) -> Color {
+        return self.syntax[scope as usize];
+    }
+    //Rgbを表示できない端末では近い色に置き換える
+    fn fallback(mut self, depth: Depth) -> Self {
+        let convert = |color: Color| match (depth, color) {
+            (Depth::True, _) => color,
+            (Depth::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(ansi256(r, g, b)),
+            (Depth::Ansi16, Color::Rgb { r, g, b }) => ansi16(r, g, b),
+            _ => color,
+        };
+        for color in [
+            &mut self.gutter,
+            &mut self.directory,
+            &mut self.header,
+            &mut self.warning,
+            &mut self.message,
+            &mut self.selection,
+        ] {
+            *color = convert(*color);
+        }
+        for color in self.syntax.iter_mut() {
+            *color = convert(*color);
+        }
+        return self;
+    }
+}
+
+//darkとlightは組み込みのテーマ。それ以外はTOMLファイルのパスとして読む
+//ファイルではbaseで元にするテーマを選び、役割ごとに"#rrggbb"か0から255の番号で色を書く。色分けは[syntax]に書く
+pub fn load(name: &str) -> Result<Theme, Error> {
+    match name {
+        "dark" => return Ok(DARK),
+        "light" => return Ok(LIGHT),
+        _ => {}
+    }
+    let error = |msg: String| Error::Theme(name.to_string(), msg);
+    let text = read_to_string(name).map_err(|e| error(e.to_string()))?;
+    let table = text
+        .parse::<Table>()
+        .map_err(|e| error(e.message().to_string()))?;
+    let mut theme = match table.get("base").map(|base| base.as_str()) {
+        None | Some(Some("dark")) => DARK,
+        Some(Some("light")) => LIGHT,
+        Some(_) => return Err(error(String::from("baseはdarkかlightです"))),
+    };
+    for (key, value) in &table {
+        let color = match key.as_str() {
+            "base" | "syntax" => continue,
+            "gutter" => &mut theme.gutter,
+            "directory" => &mut theme.directory,
+            "header" => &mut theme.header,
+            "warning" => &mut theme.warning,
+            "message" => &mut theme.message,
+            "selection" => &mut theme.selection,
+            _ => return Err(error(format!("{}という役割はありません", key))),
+        };
+        *color = parse(key, value).map_err(error)?;
+    }
+    if let Some(syntax) = table.get("syntax") {
+        let Some(syntax) = syntax.as_table() else {
+            return Err(error(String::from("syntaxは表です")));
+        };
+        for (key, value) in syntax {
+            let Some(i) = SCOPE_NAMES.iter().position(|name| name == key) else {
+                return Err(error(format!("{}という色分けはありません", key)));
+            };
+            theme.syntax[i] = parse(key, value).map_err(error)?;
+        }
+    }
+    return Ok(theme);
+}
+
+//"#rrggbb"、256色の番号、"reset"のどれか
+fn parse(key: &str, value: &Value) -> Result<Color, String> {
+    let invalid = || format!("{}の色{}が読めません", key, value);
+    if let Some(n) = value.as_integer() {
+        return u8::try_from(n).map(Color::AnsiValue).map_err(|_| invalid());
+    }
+    let s = value.as_str().ok_or_else(invalid)?;
+    if s == "reset" {
+        return Ok(Color::Reset);
+    }
+    let hex = s
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .ok_or_else(invalid)?;
+    let n = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    return Ok(rgb((n >> 16) as u8, (n >> 8) as u8, n as u8));
+}
+
+#[derive(Clone, Copy)]
+enum Depth {
+    True,
+    Ansi256,
+    Ansi16,
+}
+
+//COLORTERMとTERMから端末の色数を推測する
+fn depth() -> Depth {
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return Depth::True;
+    }
+    if var("TERM").unwrap_or_default().contains("256color") {
+        return Depth::Ansi256;
+    }
+    return Depth::Ansi16;
+}
+
+//256色の6x6x6の色と24段階の灰色のうち近い方
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (i, j, k) = (level(r), level(g), level(b));
+    let cube = (LEVELS[i], LEVELS[j], LEVELS[k]);
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = ((average.saturating_sub(8)) / 10).min(23) as u8;
+    let level = 8 + gray * 10;
+    if distance((r, g, b), (level, level, level)) < distance((r, g, b), cube) {
+        return 232 + gray;
+    }
+    return 16 + 36 * i as u8 + 6 * j as u8 + k as u8;
+}
+
+//16色のうち色相が近い色。彩度の低い色は明るさで灰色を選ぶ
+fn ansi16(r: u8, g: u8, b: u8) -> Color {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max - min < 40 {
+        return match max {
+            0..64 => Color::Black,
+            64..160 => Color::DarkGrey,
+            160..224 => Color::Grey,
+            _ => Color::White,
+        };
+    }
+    let middle = (max as u16 + min as u16) / 2;
+    let bits = (middle <= r as u16, middle <= g as u16, middle <= b as u16);
+    let bright = 200 <= max;
+    return match (bits, bright) {
+        ((true, false, false), true) => Color::Red,
+        ((true, false, false), false) => Color::DarkRed,
+        ((false, true, false), true) => Color::Green,
+        ((false, true, false), false) => Color::DarkGreen,
+        ((true, true, false), true) => Color::Yellow,
+        ((true, true, false), false) => Color::DarkYellow,
+        ((false, false, true), true) => Color::Blue,
+        ((false, false, true), false) => Color::DarkBlue,
+        ((true, false, true), true) => Color::Magenta,
+        ((true, false, true), false) => Color::DarkMagenta,
+        ((false, true, true), true) => Color::Cyan,
+        ((false, true, true), false) => Color::DarkCyan,
+        (_, true) => Color::White,
+        (_, false) => Color::Grey,
+    };
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    return d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2);
+}
@@ -0,0 +1,189 @@
+use crate::{
+    syntax::{Scope, Span},
+    Buffer,
+};
+use std::iter;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{InputEdit, Node, Parser, Point, Query, QueryCursor};
+
+//位置は(行, バイト位置)
+pub type Position = (usize, usize);
+
+//tree-sitterの構文木。編集は木に伝えておき、描く前にまとめて差分だけ解析し直す
+pub struct Tree {
+    parser: Parser,
+    query: Query,
+    tree: Option<tree_sitter::Tree>,
+    edited: bool,
+}
+
+impl Tree {
+    //文法がある言語だけ構文木を作る
+    pub fn new(name: &str) -> Option<Self> {
+        if name != "Rust" {
+            return None;
+        }
+        let language = tree_sitter_rust::LANGUAGE.into();
+        let mut parser = Parser::new();
+        parser.set_language(&language).ok()?;
+        let query = Query::new(&language, tree_sitter_rust::HIGHLIGHTS_QUERY).ok()?;
+        let tree = None;
+        let edited = true;
+        return Some(Self {
+            parser,
+            query,
+            tree,
+            edited,
+        });
+    }
+    //バッファを入れ替えたときは最初から解析する
+    pub fn reset(&mut self) {
+        self.tree = None;
+        self.edited = true;
+    }
+    //byteバイト目の(y, x)にあったremovedをinsertedに置き換えた
+    pub fn edit(&mut self, byte: usize, start: Position, removed: &str, inserted: &str) {
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&InputEdit {
+                start_byte: byte,
+                old_end_byte: byte + removed.len(),
+                new_end_byte: byte + inserted.len(),
+                start_position: point(start),
+                old_end_position: point(end_of(start, removed)),
+                new_end_position: point(end_of(start, inserted)),
+            });
+        }
+        self.edited = true;
+    }
+    //編集されていれば解析し直し、構文が変わった行の範囲を返す
+    pub fn parse(&mut self, buffer: &Buffer) -> Option<(usize, usize)> {
+        if !self.edited {
+            return None;
+        }
+        self.edited = false;
+        let lines = buffer.len_lines();
+        let mut read = |_, point: Point| {
+            if lines <= point.row {
+                return String::new();
+            }
+            let line = buffer.line(point.row);
+            let mut text = line.get(point.column..).unwrap_or("").to_string();
+            if point.row + 1 < lines {
+                text.push('\n');
+            }
+            return text;
+        };
+        let tree = self.parser.parse_with(&mut read, self.tree.as_ref())?;
+        let changed = match &self.tree {
+            Some(old) => old
+                .changed_ranges(&tree)
+                .map(|range| (range.start_point.row, range.end_point.row + 1))
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1))),
+            None => Some((0, lines)),
+        };
+        self.tree = Some(tree);
+        return changed;
+    }
+    //行iの色分け。同じ範囲に複数の名前が付いたときは、先に書かれたパターンを使う
+    pub fn spans(&self, buffer: &Buffer, i: usize) -> Vec<Span> {
+        let Some(tree) = &self.tree else {
+            return Vec::new();
+        };
+        let len = buffer.line(i).len();
+        let mut cursor = QueryCursor::new();
+        cursor.set_point_range(Point::new(i, 0)..Point::new(i + 1, 0));
+        let text = |node: Node| {
+            let start = node.start_position();
+            let end = node.end_position();
+            iter::once(buffer.text((start.row, start.column), (end.row, end.column)))
+        };
+        let mut captures = cursor.captures(&self.query, tree.root_node(), text);
+        let mut spans: Vec<Span> = Vec::new();
+        while let Some((m, k)) = captures.next() {
+            let capture = m.captures[*k];
+            let name = self.query.capture_names()[capture.index as usize];
+            let Some(scope) = scope(name) else {
+                continue;
+            };
+            let start = capture.node.start_position();
+            let end = capture.node.end_position();
+            let a = if start.row < i { 0 } else { start.column };
+            let b = if i < end.row { len } else { end.column };
+            if spans.iter().any(|&(x, y, _)| (x, y) == (a, b)) {
+                continue;
+            }
+            spans.push((a, b, scope));
+        }
+        return spans;
+    }
+    //startからendを含み、それより大きい最小の名前付きの節の範囲
+    pub fn enclosing(&self, start: Position, end: Position) -> Option<(Position, Position)> {
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.named_descendant_for_point_range(point(start), point(end))?;
+        while range(node) == (start, end) {
+            node = node.parent()?;
+        }
+        return Some(range(node));
+    }
+    //カーソルのある節の親の先頭。すでに先頭にいるときはさらに上の親へ
+    pub fn parent(&self, position: Position) -> Option<Position> {
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.named_descendant_for_point_range(point(position), point(position))?;
+        loop {
+            node = node.parent()?;
+            if range(node).0 != position {
+                return Some(range(node).0);
+            }
+        }
+    }
+    //カーソルから始まる一番外側の節の次の兄弟の先頭。兄弟がなければ親の次の兄弟へ
+    pub fn next_sibling(&self, position: Position) -> Option<Position> {
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.named_descendant_for_point_range(point(position), point(position))?;
+        while let Some(parent) = node.parent() {
+            if range(parent).0 != range(node).0 {
+                break;
+            }
+            node = parent;
+        }
+        loop {
+            if let Some(sibling) = node.next_named_sibling() {
+                return Some(range(sibling).0);
+            }
+            node = node.parent()?;
+        }
+    }
+}
+
+fn point(position: Position) -> Point {
+    return Point::new(position.0, position.1);
+}
+
+fn range(node: Node) -> (Position, Position) {
+    let start = node.start_position();
+    let end = node.end_position();
+    return ((start.row, start.column), (end.row, end.column));
+}
+
+//startにtextを書いたときの終わりの位置
+fn end_of(start: Position, text: &str) -> Position {
+    return match text.rfind('\n') {
+        Some(i) => (start.0 + text.matches('\n').count(), text.len() - i - 1),
+        None => (start.0, start.1 + text.len()),
+    };
+}
+
+//highlights.scmの名前を色分けの種類にする
+fn scope(name: &str) -> Option<Scope> {
+    let scope = match name.split('.').next()? {
+        "comment" => Scope::Comment,
+        "string" | "escape" => Scope::String,
+        "constant" => Scope::Number,
+        "keyword" => Scope::Keyword,
+        "type" | "constructor" => Scope::Type,
+        "function" => Scope::Function,
+        "attribute" | "label" => Scope::Key,
+        _ => return None,
+    };
+    return Some(scope);
+}
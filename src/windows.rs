@@ -0,0 +1,194 @@
+use crate::Cursor;
+
+//画面上の範囲(x, y, 幅, 高さ)
+pub type Rect = (u16, u16, u16, u16);
+
+#[derive(Clone, Copy)]
+pub enum Split {
+    //上下に分ける
+    Horizontal,
+    //左右に分ける
+    Vertical,
+}
+
+//分割した画面の1つ。表示するバッファの番号と、そのバッファでのカーソルと表示位置を持つ
+#[derive(Clone)]
+pub struct Pane {
+    pub buffer: usize,
+    pub view: (Cursor, usize),
+}
+
+enum Node {
+    Leaf(Pane),
+    Split(Split, Box<Node>, Box<Node>),
+}
+
+//画面の分割を木で持つ。葉を左上から順に数えた番号で画面を指す
+pub struct Windows {
+    root: Node,
+    pub focus: usize,
+}
+
+impl Windows {
+    pub fn new() -> Self {
+        let root = Node::Leaf(Pane {
+            buffer: 0,
+            view: (Cursor::new(), 0),
+        });
+        let focus = 0;
+        return Self { root, focus };
+    }
+    pub fn len(&self) -> usize {
+        return self.panes().len();
+    }
+    pub fn panes(&self) -> Vec<&Pane> {
+        let mut panes = Vec::new();
+        leaves(&self.root, &mut panes);
+        return panes;
+    }
+    pub fn pane(&mut self) -> &mut Pane {
+        let mut panes = Vec::new();
+        leaves_mut(&mut self.root, &mut panes);
+        return panes.swap_remove(self.focus);
+    }
+    //rectを分けた各画面の範囲と、左右に分けた境目の縦線(x, y, 1, 高さ)
+    pub fn layout(&self, rect: Rect) -> (Vec<Rect>, Vec<Rect>) {
+        let mut rects = Vec::new();
+        let mut borders = Vec::new();
+        layout(&self.root, rect, &mut rects, &mut borders);
+        return (rects, borders);
+    }
+    //今の画面を2つに分け、同じバッファを同じ位置で表示する
+    pub fn split(&mut self, split: Split) {
+        let mut i = self.focus;
+        split_leaf(&mut self.root, &mut i, split);
+    }
+    //今の画面を閉じ、次の画面に移る。最後の1つは閉じない
+    pub fn close(&mut self) -> bool {
+        if self.len() == 1 {
+            return false;
+        }
+        let mut i = self.focus;
+        let root = std::mem::replace(
+            &mut self.root,
+            Node::Leaf(Pane {
+                buffer: 0,
+                view: (Cursor::new(), 0),
+            }),
+        );
+        self.root = remove_leaf(root, &mut i).unwrap();
+        self.focus = self.focus.min(self.len() - 1);
+        return true;
+    }
+    pub fn next(&mut self) {
+        self.focus = (self.focus + 1) % self.len();
+    }
+    //バッファiを閉じたとき、それを表示していた画面はcurrentを表示し、後ろのバッファの番号を詰める
+    pub fn remove_buffer(&mut self, i: usize, current: usize) {
+        let mut panes = Vec::new();
+        leaves_mut(&mut self.root, &mut panes);
+        for pane in panes {
+            if pane.buffer == i {
+                pane.buffer = current;
+                pane.view = (Cursor::new(), 0);
+            } else if i < pane.buffer {
+                pane.buffer -= 1;
+            }
+        }
+    }
+}
+
+fn leaves<'a>(node: &'a Node, panes: &mut Vec<&'a Pane>) {
+    match node {
+        Node::Leaf(pane) => panes.push(pane),
+        Node::Split(_, a, b) => {
+            leaves(a, panes);
+            leaves(b, panes);
+        }
+    }
+}
+
+fn leaves_mut<'a>(node: &'a mut Node, panes: &mut Vec<&'a mut Pane>) {
+    match node {
+        Node::Leaf(pane) => panes.push(pane),
+        Node::Split(_, a, b) => {
+            leaves_mut(a, panes);
+            leaves_mut(b, panes);
+        }
+    }
+}
+
+//上下は半分ずつに分け、各画面の最後の行のステータス行を境目にする
+//ただし高さが足りなければ、どちらの画面にも少なくともステータス行の1行を残す
+//左右は境目に1列使う
+fn layout(node: &Node, rect: Rect, rects: &mut Vec<Rect>, borders: &mut Vec<Rect>) {
+    let (x, y, width, height) = rect;
+    match node {
+        Node::Leaf(_) => rects.push(rect),
+        Node::Split(Split::Horizontal, a, b) => {
+            let h = (height / 2)
+                .max(min_height(a))
+                .min(height.saturating_sub(min_height(b)));
+            layout(a, (x, y, width, h), rects, borders);
+            layout(b, (x, y + h, width, height - h), rects, borders);
+        }
+        Node::Split(Split::Vertical, a, b) => {
+            let w = width.saturating_sub(1) / 2;
+            layout(a, (x, y, w, height), rects, borders);
+            borders.push((x + w, y, 1, height));
+            let rest = width.saturating_sub(w + 1);
+            layout(b, (x + w + 1, y, rest, height), rects, borders);
+        }
+    }
+}
+
+//すべての画面にステータス行を1行ずつ描くのに要る高さ
+fn min_height(node: &Node) -> u16 {
+    return match node {
+        Node::Leaf(_) => 1,
+        Node::Split(Split::Horizontal, a, b) => min_height(a) + min_height(b),
+        Node::Split(Split::Vertical, a, b) => min_height(a).max(min_height(b)),
+    };
+}
+
+//i番目の葉を、同じ画面を2つ並べた節に置き換える
+fn split_leaf(node: &mut Node, i: &mut usize, split: Split) -> bool {
+    match node {
+        Node::Leaf(pane) => {
+            if *i == 0 {
+                let a = Box::new(Node::Leaf(pane.clone()));
+                let b = Box::new(Node::Leaf(pane.clone()));
+                *node = Node::Split(split, a, b);
+                return true;
+            }
+            *i -= 1;
+            return false;
+        }
+        Node::Split(_, a, b) => {
+            return split_leaf(a, i, split) || split_leaf(b, i, split);
+        }
+    }
+}
+
+//i番目の葉を取り除いた木を返す。葉を取り除いた節は残った方の子に置き換える
+fn remove_leaf(node: Node, i: &mut usize) -> Option<Node> {
+    match node {
+        Node::Leaf(pane) => {
+            if *i == 0 {
+                *i = usize::MAX;
+                return None;
+            }
+            *i = i.wrapping_sub(1);
+            return Some(Node::Leaf(pane));
+        }
+        Node::Split(split, a, b) => {
+            let a = remove_leaf(*a, i);
+            let b = remove_leaf(*b, i);
+            return match (a, b) {
+                (Some(a), Some(b)) => Some(Node::Split(split, Box::new(a), Box::new(b))),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            };
+        }
+    }
+}
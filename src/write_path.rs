@@ -1,7 +1,12 @@
-use crate::{Cursor, Dir, Mode};
+use crate::{
+    command::Command,
+    config,
+    keys::{self, Chord, Input},
+    theme, Cursor, Dir, Mode,
+};
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
-    event::{read, Event, KeyCode, KeyModifiers},
+    event::{Event, KeyCode},
     style::{Color, SetForegroundColor},
     terminal::{window_size, Clear, ClearType},
 };
@@ -14,31 +19,28 @@ use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 pub struct WritePath {
     cursor: Cursor,
     msg: String,
-    msg_color: SetForegroundColor,
+    //msgが上書きの確認か
+    warning: bool,
     input_mode: InputMode,
-    pub buffer: Vec<String>,
     path: String,
+    chord: Chord,
 }
 
 impl WritePath {
     pub fn new() -> Self {
         let cursor = Cursor::new();
-        let msg = format!("{}", absolute(".").unwrap().to_str().unwrap());
-        let msg_color = SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 255,
-            b: 255,
-        });
+        let msg = absolute(".").unwrap().to_str().unwrap().to_string();
+        let warning = false;
         let input_mode = InputMode::Write;
-        let buffer = Vec::new();
         let path = String::new();
+        let chord = Chord::new();
         return Self {
             cursor,
             msg,
-            msg_color,
+            warning,
             input_mode,
-            buffer,
             path,
+            chord,
         };
     }
     pub fn run(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &mut Dir) -> String {
@@ -54,11 +56,7 @@ impl WritePath {
             stdout,
             "{}{}[名前を付けて保存]{}",
             MoveTo(0, 0),
-            SetForegroundColor(Color::Rgb {
-                r: 0,
-                g: 255,
-                b: 255
-            }),
+            SetForegroundColor(theme::current().header),
             SetForegroundColor(Color::Reset)
         )
         .unwrap();
@@ -72,11 +70,16 @@ impl WritePath {
             stdout,
             "{}{}{}{}",
             MoveTo(0, height - 1),
-            self.msg_color,
+            SetForegroundColor(if self.warning {
+                theme::current().warning
+            } else {
+                theme::current().message
+            }),
             self.msg,
             SetForegroundColor(Color::Reset)
         )
         .unwrap();
+        write!(stdout, " {}", self.chord.pending()).unwrap();
         write!(
             stdout,
             "{}ファイル名を入力 > {}{}",
@@ -89,35 +92,49 @@ impl WritePath {
     }
     fn input(&mut self, stdout: &mut Stdout, mode: &mut Mode, dir: &mut Dir) -> String {
         let mut path = String::new();
-        if let Ok(Event::Key(key)) = read() {
-            match key.code {
-                KeyCode::Tab => {
+        if let Ok(Event::Key(key)) = keys::read(None) {
+            let input = self.chord.input(&config::current().keymaps.save_as, key);
+            match input {
+                Input::Command(Command::ToggleInput) => {
                     if matches!(self.input_mode, InputMode::Select) {
                         self.input_mode = InputMode::Write;
                     } else {
                         self.input_mode = InputMode::Select;
                     }
                 }
-                KeyCode::Esc => {
+                Input::Command(Command::FocusList) => {
                     self.input_mode = InputMode::Select;
                 }
-                KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
+                Input::Command(Command::Cancel) => {
                     self.back(stdout, mode);
                 }
-                KeyCode::Up if matches!(self.input_mode, InputMode::Select) => {
-                    if 0 < dir.cursor.y {
-                        dir.cursor.y -= 1;
-                    }
+                Input::Command(Command::MoveUp)
+                    if matches!(self.input_mode, InputMode::Select) && 0 < dir.cursor.y =>
+                {
+                    dir.cursor.y -= 1;
                 }
-                KeyCode::Down if matches!(self.input_mode, InputMode::Select) => {
-                    if dir.cursor.y < dir.dirs.len() + dir.files.len() {
-                        dir.cursor.y += 1;
-                    }
+                Input::Command(Command::MoveDown)
+                    if matches!(self.input_mode, InputMode::Select)
+                        && dir.cursor.y < dir.dirs.len() + dir.files.len() =>
+                {
+                    dir.cursor.y += 1;
                 }
-                KeyCode::Left if matches!(self.input_mode, InputMode::Write) => self.left(),
-                KeyCode::Right if matches!(self.input_mode, InputMode::Write) => self.right(),
-                KeyCode::Backspace if matches!(self.input_mode, InputMode::Write) => self.delete(),
-                KeyCode::Enter => {
+                Input::Command(Command::MoveLeft)
+                    if matches!(self.input_mode, InputMode::Write) =>
+                {
+                    self.left()
+                }
+                Input::Command(Command::MoveRight)
+                    if matches!(self.input_mode, InputMode::Write) =>
+                {
+                    self.right()
+                }
+                Input::Command(Command::DeleteBackward)
+                    if matches!(self.input_mode, InputMode::Write) =>
+                {
+                    self.delete()
+                }
+                Input::Command(Command::Select) => {
                     if matches!(self.input_mode, InputMode::Select) {
                         self.select(dir);
                     } else {
@@ -127,20 +144,24 @@ impl WritePath {
                         }
                     }
                 }
-                KeyCode::Char('y') if matches!(self.input_mode, InputMode::Check) => {
-                    path = dir.files[dir.cursor.y - 1 - dir.dirs.len()]
-                        .path()
-                        .to_str()
-                        .unwrap()
-                        .to_string();
-                    self.back(stdout, mode);
-                }
-                KeyCode::Char('n') if matches!(self.input_mode, InputMode::Check) => {
-                    self.input_mode = InputMode::Select;
-                }
-                KeyCode::Char(c) if matches!(self.input_mode, InputMode::Write) => {
-                    self.typing(c);
-                }
+                //上書きの確認の答えと、ファイル名の入力はキーのまま扱う
+                Input::Key(key) => match key.code {
+                    KeyCode::Char('y') if matches!(self.input_mode, InputMode::Check) => {
+                        path = dir.files[dir.cursor.y - 1 - dir.dirs.len()]
+                            .path()
+                            .to_str()
+                            .unwrap()
+                            .to_string();
+                        self.back(stdout, mode);
+                    }
+                    KeyCode::Char('n') if matches!(self.input_mode, InputMode::Check) => {
+                        self.input_mode = InputMode::Select;
+                    }
+                    KeyCode::Char(c) if matches!(self.input_mode, InputMode::Write) => {
+                        self.typing(c);
+                    }
+                    _ => {}
+                },
                 _ => {}
             }
         }
@@ -234,23 +255,15 @@ impl WritePath {
                     .unwrap(),
                 "このファイルは既に存在します。上書きしますか？(y/n)"
             );
-            self.msg_color = SetForegroundColor(Color::Rgb {
-                r: 215,
-                g: 135,
-                b: 95,
-            });
+            self.warning = true;
             self.input_mode = InputMode::Check;
         }
     }
     fn set(&mut self, dir: &mut Dir) {
         dir.set();
-        self.msg = format!("{}", dir.dir_path.to_str().unwrap());
+        self.msg = dir.dir_path.to_str().unwrap().to_string();
         dir.cursor.y = 0;
-        self.msg_color = SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 255,
-            b: 255,
-        });
+        self.warning = false;
     }
 }
 